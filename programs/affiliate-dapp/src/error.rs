@@ -8,4 +8,126 @@ pub enum CustomError {
     InvalidAccountOwner,
     #[msg("Price should not be negative")]
     InvalidPrice,
-}
\ No newline at end of file
+    #[msg("Only the campaign company can perform this action")]
+    Unauthorized,
+    #[msg("Campaign is not active")]
+    CampaignNotActive,
+    #[msg("Campaign is already paused")]
+    CampaignAlreadyPaused,
+    #[msg("Campaign is already active")]
+    CampaignAlreadyActive,
+    #[msg("Commission must be between 0 and 10000 basis points")]
+    InvalidCommissionBps,
+    #[msg("Campaign details must be at most 200 characters")]
+    CampaignDetailsTooLong,
+    #[msg("Account is not a valid legacy account for this migration")]
+    InvalidLegacyAccount,
+    #[msg("Campaign escrow has no NFTs left to sell")]
+    SoldOut,
+    #[msg("Amount must be greater than zero")]
+    InvalidAmount,
+    #[msg("Campaign escrow still holds inventory")]
+    InventoryNotEmpty,
+    #[msg("No unclaimed earnings for this affiliate link")]
+    NothingToClaim,
+    #[msg("Protocol fee must be between 0 and 10000 basis points")]
+    InvalidProtocolFeeBps,
+    #[msg("Treasury does not match the protocol config")]
+    InvalidTreasury,
+    #[msg("Protocol is paused")]
+    ProtocolPaused,
+    #[msg("Token program does not match the campaign")]
+    InvalidTokenProgram,
+    #[msg("Instruction is not available for this campaign mode")]
+    InvalidCampaignMode,
+    #[msg("Base URI must be between 1 and 200 characters")]
+    InvalidBaseUri,
+    #[msg("Mint, escrow and company token accounts are required to return escrowed NFTs")]
+    MissingEscrowAccounts,
+    #[msg("Campaign already has a group mint")]
+    GroupAlreadyCreated,
+    #[msg("Campaign has no group mint")]
+    GroupNotCreated,
+    #[msg("NFT is not a member of the campaign group")]
+    NotGroupMember,
+    #[msg("Campaign group mint account is required")]
+    MissingGroupMint,
+    #[msg("Payment mint does not match the campaign")]
+    InvalidPaymentMint,
+    #[msg("Token payment accounts are required for campaigns priced in a token")]
+    MissingPaymentAccounts,
+    #[msg("Price table has too many currencies")]
+    PriceTableTooLarge,
+    #[msg("Currency is listed more than once or is the campaign's own payment currency")]
+    DuplicateCurrency,
+    #[msg("Campaign does not accept this currency")]
+    UnsupportedCurrency,
+    #[msg("Affiliate earnings account for the currency is required")]
+    MissingCurrencyEarnings,
+    #[msg("Price feed account is not a valid price feed")]
+    InvalidPriceFeed,
+    #[msg("Price feed is not trading or its confidence interval is too wide")]
+    PriceFeedUnavailable,
+    #[msg("Price feed has not been updated recently enough")]
+    StalePriceFeed,
+    #[msg("Campaign price feed account is required")]
+    MissingPriceFeed,
+    #[msg("USD pricing is only available for campaigns paid in lamports")]
+    PriceFeedRequiresLamports,
+    #[msg("Total price is above the maximum the buyer accepted")]
+    MaxTotalPriceExceeded,
+    #[msg("Remaining accounts don't match the purchase quantity")]
+    InvalidRemainingAccounts,
+    #[msg("Metadata account is not the Metaplex metadata of the NFT mint")]
+    InvalidMetadataAccount,
+    #[msg("Creator account doesn't match the NFT metadata")]
+    InvalidCreatorAccount,
+    #[msg("Creator royalties exceed the company's share of the sale")]
+    RoyaltyExceedsProceeds,
+    #[msg("NFT is not a verified member of the campaign collection")]
+    NotCollectionMember,
+    #[msg("NFT mint must have 0 decimals")]
+    InvalidNftDecimals,
+    #[msg("NFT mint supply must match the escrowed amount")]
+    NftSupplyMismatch,
+    #[msg("Campaign inventory has no room for more entries")]
+    InventoryFull,
+    #[msg("NFT is not listed in the campaign inventory")]
+    NotInInventory,
+    #[msg("Inventory entries don't match the campaign available supply")]
+    InventoryOutOfSync,
+    #[msg("Campaign inventory account is required for random selection")]
+    MissingInventory,
+    #[msg("Not available for the campaign's selection mode")]
+    InvalidSelectionMode,
+    #[msg("Random draws must be delivered before the inventory changes or the campaign closes")]
+    PendingRandomDraws,
+    #[msg("Account doesn't match the random draw")]
+    InvalidRandomDraw,
+    #[msg("SlotHashes sysvar has no entries")]
+    InvalidSlotHashes,
+    #[msg("NFT mint, escrow and recipient token accounts are required when buyers pick the NFT")]
+    MissingNftAccounts,
+    #[msg("A random draw account is required for random selection")]
+    MissingRandomDrawAccounts,
+    #[msg("Auction start price must be above its end price and the decay interval positive")]
+    InvalidAuction,
+    #[msg("Auction has not started yet")]
+    AuctionNotStarted,
+    #[msg("Auctions only sell in the campaign's own currency")]
+    AuctionCurrencyOnly,
+    #[msg("Random draws only sell in lamports")]
+    RandomDrawCurrency,
+    #[msg("The campaign's NFT is required and must still have a mint authority to join a group")]
+    CampaignNftCannotJoinGroup,
+    #[msg("No slot after the purchase has a hash yet, try again in the next slot")]
+    DrawNotReady,
+    #[msg("The purchase slot left the SlotHashes window, renew the draw")]
+    DrawExpired,
+    #[msg("The draw can still be delivered")]
+    DrawNotExpired,
+    #[msg("Random draws are delivered in purchase order")]
+    DrawOutOfOrder,
+    #[msg("NFT metadata account is required to check royalties for random selection")]
+    MissingNftMetadata,
+}
@@ -0,0 +1,83 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token_2022::{
+        self,
+        spl_token_2022::{
+            extension::{BaseStateWithExtensions, StateWithExtensions},
+            state::Mint as MintState,
+        },
+    },
+    token_interface::{token_member_initialize, TokenMemberInitialize},
+};
+use spl_token_group_interface::state::TokenGroupMember;
+
+use crate::{error::CustomError, payment::transfer_lamports, state::NFTCampaign};
+
+// Bytes the TokenGroupMember extension adds to a mint: TLV type and length headers plus the entry
+pub const GROUP_MEMBER_TLV_LEN: usize = 4 + std::mem::size_of::<TokenGroupMember>();
+
+// Group recorded in a mint's TokenGroupMember extension, if it has one
+pub fn group_of_member(mint: &AccountInfo) -> Option<Pubkey> {
+    if *mint.owner != token_2022::ID {
+        return None;
+    }
+    let data = mint.try_borrow_data().ok()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data).ok()?;
+    let member = state.get_extension::<TokenGroupMember>().ok()?;
+    Some(member.group)
+}
+
+// Campaigns without a group accept any mint; grouped campaigns only handle their own members
+pub fn require_group_member(campaign: &NFTCampaign, mint: &AccountInfo) -> Result<()> {
+    if campaign.group_mint == Pubkey::default() {
+        return Ok(());
+    }
+    require!(
+        group_of_member(mint) == Some(campaign.group_mint),
+        CustomError::NotGroupMember
+    );
+    Ok(())
+}
+
+// Accounts needed to add a mint to a campaign group
+pub struct GroupMemberAccounts<'info> {
+    pub payer: AccountInfo<'info>,
+    pub member_mint: AccountInfo<'info>,
+    pub member_mint_authority: AccountInfo<'info>,
+    pub group_mint: AccountInfo<'info>,
+    pub group_update_authority: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+}
+
+// Registers `member_mint` in the group. Token-2022 reallocs the mint to append the member
+// entry, so its rent is topped up first. The group update authority is always the
+// campaign's [b"mint_authority", campaign] PDA, which signs through `signer_seeds`.
+pub fn register_group_member(
+    accounts: &GroupMemberAccounts,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let new_len = accounts.member_mint.data_len() + GROUP_MEMBER_TLV_LEN;
+    let shortfall = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(accounts.member_mint.lamports());
+    transfer_lamports(
+        &accounts.payer,
+        &accounts.member_mint,
+        &accounts.system_program,
+        shortfall,
+    )?;
+
+    token_member_initialize(CpiContext::new_with_signer(
+        accounts.token_program.clone(),
+        TokenMemberInitialize {
+            token_program_id: accounts.token_program.clone(),
+            member: accounts.member_mint.clone(),
+            member_mint: accounts.member_mint.clone(),
+            member_mint_authority: accounts.member_mint_authority.clone(),
+            group: accounts.group_mint.clone(),
+            group_update_authority: accounts.group_update_authority.clone(),
+        },
+        signer_seeds,
+    ))
+}
@@ -0,0 +1,156 @@
+use anchor_lang::{prelude::*, solana_program::program::invoke_signed};
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+use crate::{error::CustomError, state::*};
+
+#[derive(Accounts)]
+pub struct ClaimEarnings<'info> {
+    #[account(mut)]
+    pub influencer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"affiliate_link", influencer.key().as_ref(), affiliate_link.campaign.as_ref()],
+        bump,
+        has_one = influencer @ CustomError::InvalidInfluencer,
+    )]
+    pub affiliate_link: Account<'info, AffiliateLink>,
+
+    #[account(
+        mut,
+        seeds = [b"affiliate_vault", affiliate_link.key().as_ref()],
+        bump,
+    )]
+    pub affiliate_vault: SystemAccount<'info>,
+
+    // Claims a price-table currency instead of the campaign's own payment currency
+    #[account(
+        mut,
+        seeds = [b"affiliate_earnings", affiliate_link.key().as_ref(), affiliate_earnings.currency.as_ref()],
+        bump,
+        has_one = affiliate_link,
+    )]
+    pub affiliate_earnings: Option<Account<'info, AffiliateEarnings>>,
+
+    // Only when the claimed currency is a token; must be its mint
+    pub payment_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = payment_mint,
+        associated_token::authority = affiliate_vault,
+        associated_token::token_program = payment_token_program,
+    )]
+    pub vault_payment_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = payment_mint,
+        token::authority = influencer,
+        token::token_program = payment_token_program,
+    )]
+    pub influencer_payment_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub payment_token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when an influencer withdraws commissions from the vault
+#[event]
+pub struct EarningsClaimedEvent {
+    pub affiliate_link: Pubkey,
+    pub influencer: Pubkey,
+    pub currency: Pubkey,
+    pub amount: u64,
+    pub claimed_earnings: u64,
+    pub claimed_at: i64,
+}
+
+pub fn claim_earnings_instruction(ctx: Context<ClaimEarnings>) -> Result<()> {
+    let affiliate_link = &ctx.accounts.affiliate_link;
+    let (currency, amount) = match &ctx.accounts.affiliate_earnings {
+        Some(earnings) => (earnings.currency, earnings.unclaimed_earnings),
+        None => (affiliate_link.payment_mint, affiliate_link.unclaimed_earnings),
+    };
+    require!(amount > 0, CustomError::NothingToClaim);
+
+    // The vault is a system-owned PDA, so it signs its own transfer
+    let affiliate_link_key = affiliate_link.key();
+    let vault_seeds = [
+        b"affiliate_vault",
+        affiliate_link_key.as_ref(),
+        &[ctx.bumps.affiliate_vault],
+    ];
+
+    if currency == Pubkey::default() {
+        let transfer_to_influencer_ix = anchor_lang::solana_program::system_instruction::transfer(
+            &ctx.accounts.affiliate_vault.key(),
+            &ctx.accounts.influencer.key(),
+            amount,
+        );
+        invoke_signed(
+            &transfer_to_influencer_ix,
+            &[
+                ctx.accounts.affiliate_vault.to_account_info(),
+                ctx.accounts.influencer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[&vault_seeds[..]],
+        )?;
+    } else {
+        let (
+            Some(payment_mint),
+            Some(vault_payment_account),
+            Some(influencer_payment_account),
+            Some(payment_token_program),
+        ) = (
+            &ctx.accounts.payment_mint,
+            &ctx.accounts.vault_payment_account,
+            &ctx.accounts.influencer_payment_account,
+            &ctx.accounts.payment_token_program,
+        )
+        else {
+            return err!(CustomError::MissingPaymentAccounts);
+        };
+        require_keys_eq!(payment_mint.key(), currency, CustomError::InvalidPaymentMint);
+
+        // Any transfer fee of the payment mint is taken from the claimed amount
+        transfer_checked(
+            CpiContext::new_with_signer(
+                payment_token_program.to_account_info(),
+                TransferChecked {
+                    from: vault_payment_account.to_account_info(),
+                    mint: payment_mint.to_account_info(),
+                    to: influencer_payment_account.to_account_info(),
+                    authority: ctx.accounts.affiliate_vault.to_account_info(),
+                },
+                &[&vault_seeds[..]],
+            ),
+            amount,
+            payment_mint.decimals,
+        )?;
+    }
+
+    let (unclaimed_earnings, claimed_earnings) = match ctx.accounts.affiliate_earnings.as_deref_mut() {
+        Some(earnings) => (&mut earnings.unclaimed_earnings, &mut earnings.claimed_earnings),
+        None => {
+            let affiliate_link: &mut AffiliateLink = &mut ctx.accounts.affiliate_link;
+            (&mut affiliate_link.unclaimed_earnings, &mut affiliate_link.claimed_earnings)
+        }
+    };
+    *unclaimed_earnings = 0;
+    *claimed_earnings = claimed_earnings
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    emit!(EarningsClaimedEvent {
+        affiliate_link: affiliate_link_key,
+        influencer: ctx.accounts.influencer.key(),
+        currency,
+        amount,
+        claimed_earnings: *claimed_earnings,
+        claimed_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
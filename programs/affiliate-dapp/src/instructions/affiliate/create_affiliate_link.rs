@@ -1,35 +1,66 @@
-use anchor_lang::prelude::*;
-use crate::state::*;
+use anchor_lang::{prelude::*, solana_program::program::invoke};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+use crate::{error::CustomError, state::*};
 
 #[derive(Accounts)]
-#[instruction(campaign_name: String)]  // Remove campaign_id parameter if not used
 pub struct CreateAffiliateLink<'info> {
     #[account(
         init,
         payer = influencer,
-        space = 8 + 32 + 32 + 8 + 8 + 8,  // 8 (discriminator) + struct size (including created_at)
-        seeds = [b"affiliate_link", influencer.key().as_ref(), campaign_name.as_bytes()],
+        space = 8 + AffiliateLink::INIT_SPACE,
+        seeds = [b"affiliate_link", influencer.key().as_ref(), campaign.key().as_ref()],
         bump,
     )]
     pub affiliate_link: Account<'info, AffiliateLink>,
     
     #[account(
         mut,  // Mark as mutable since we're updating affiliates_count
-        seeds = [b"nft_campaign", campaign_name.as_bytes()],
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
         bump,
     )]
     pub campaign: Account<'info, NFTCampaign>,
     
+    // Lamport-only PDA that collects this link's commissions
+    #[account(
+        mut,
+        seeds = [b"affiliate_vault", affiliate_link.key().as_ref()],
+        bump,
+    )]
+    pub affiliate_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"marketplace_authority"],
+        bump = marketplace_authority.bump,
+        constraint = !marketplace_authority.paused @ CustomError::ProtocolPaused,
+    )]
+    pub marketplace_authority: Account<'info, ProtocolConfig>,
+
     #[account(mut)]
     pub influencer: Signer<'info>,
-    
+
+    // Only for campaigns priced in a token: the vault's token account for commissions
+    #[account(address = campaign.payment_mint @ CustomError::InvalidPaymentMint)]
+    pub payment_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = influencer,
+        associated_token::mint = payment_mint,
+        associated_token::authority = affiliate_vault,
+        associated_token::token_program = payment_token_program,
+    )]
+    pub vault_payment_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub payment_token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+
     pub system_program: Program<'info, System>
 }
 
 // And update your instruction handler
 pub fn create_affiliate_link_instruction(
     ctx: Context<CreateAffiliateLink>,
-    _campaign_name: String,  // Keep the campaign name parameter
 ) -> Result<()> {
     let affiliate_link = &mut ctx.accounts.affiliate_link;
     let campaign = &mut ctx.accounts.campaign;
@@ -38,9 +69,48 @@ pub fn create_affiliate_link_instruction(
     affiliate_link.influencer = ctx.accounts.influencer.key();
     affiliate_link.mints_count = 0;
     affiliate_link.earnings = 0;
+    affiliate_link.claimed_earnings = 0;
+    affiliate_link.unclaimed_earnings = 0;
     affiliate_link.created_at = Clock::get()?.unix_timestamp;  // Set creation timestamp
+    affiliate_link.payment_mint = campaign.payment_mint;
+    if campaign.payment_mint != Pubkey::default() {
+        require!(
+            ctx.accounts.vault_payment_account.is_some(),
+            CustomError::MissingPaymentAccounts
+        );
+    }
     
     campaign.affiliates_count += 1;
+
+    fund_affiliate_vault(
+        &ctx.accounts.influencer.to_account_info(),
+        &ctx.accounts.affiliate_vault.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
     
+    Ok(())
+}
+
+// Tops the vault up to the rent-exempt minimum so commissions of any size can be deposited
+pub fn fund_affiliate_vault<'info>(
+    payer: &AccountInfo<'info>,
+    affiliate_vault: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(0);
+    let shortfall = rent_exempt_minimum.saturating_sub(affiliate_vault.lamports());
+    if shortfall == 0 {
+        return Ok(());
+    }
+
+    let fund_vault_ix = anchor_lang::solana_program::system_instruction::transfer(
+        payer.key,
+        affiliate_vault.key,
+        shortfall,
+    );
+    invoke(
+        &fund_vault_ix,
+        &[payer.clone(), affiliate_vault.clone(), system_program.clone()],
+    )?;
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,215 @@
+use anchor_lang::{prelude::*, solana_program::sysvar::slot_hashes};
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+use crate::{error::CustomError, group::*, metadata::*, payment::fund_rent_shortfall, random::*, state::*};
+
+// Anyone can deliver a draw once a slot after its purchase has a hash: the NFT can only
+// go to the recorded recipient, and draws are delivered in purchase order so whoever
+// delivers can't pick which draw takes which NFT
+#[derive(Accounts)]
+pub struct DeliverRandomDraw<'info> {
+    // Funds the recipient token account if it doesn't exist yet
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"random_draw",
+            campaign.key().as_ref(),
+            random_draw.sequence.to_le_bytes().as_ref(),
+        ],
+        bump = random_draw.bump,
+        has_one = campaign @ CustomError::InvalidRandomDraw,
+        has_one = recipient @ CustomError::InvalidRandomDraw,
+        has_one = rent_payer @ CustomError::InvalidRandomDraw,
+        close = rent_payer,
+    )]
+    pub random_draw: Account<'info, RandomDraw>,
+
+    /// CHECK: Wallet the draw was bought for, checked against the draw
+    pub recipient: UncheckedAccount<'info>,
+    /// CHECK: Paid for the draw record, checked against the draw
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"inventory", campaign.key().as_ref()],
+        bump = inventory.bump,
+    )]
+    pub inventory: Box<Account<'info, CampaignInventory>>,
+    /// CHECK: Address checked against the SlotHashes sysvar, read in draw_seed
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+
+    // The mint the draw picks, checked against the inventory
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: Metaplex metadata PDA of nft_mint, empty if it has none; checked in load_royalty
+    pub nft_metadata: UncheckedAccount<'info>,
+    /// CHECK: Receives the company share held by the draw, checked against the campaign
+    #[account(mut, address = campaign.company @ CustomError::InvalidAccountOwner)]
+    pub company: UncheckedAccount<'info>,
+
+    /// CHECK: The PDA for the NFT escrow account
+    #[account(
+        seeds = [b"nft_escrow", campaign.key().as_ref()],
+        bump,
+    )]
+    pub nft_escrow: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = nft_escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_pda_nft_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = nft_mint,
+        associated_token::authority = recipient,
+        associated_token::token_program = token_program
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = campaign.token_program @ CustomError::InvalidTokenProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when a draw picks its NFT and delivers it; the inputs let anyone recompute the index
+#[event]
+pub struct RandomDrawEvent {
+    pub campaign: Pubkey,
+    pub buyer: Pubkey,
+    pub recipient: Pubkey,
+    pub sequence: u64,
+    pub draw_slot: u64,
+    pub slot: u64,
+    pub slot_hash: [u8; 32],
+    pub seed: [u8; 32],
+    pub inventory_len: u64,
+    pub index: u64,
+    pub nft_mint: Pubkey,
+}
+
+// The drawn NFT's creators, passed in remaining_accounts as for a lamport sale, get their
+// royalties out of the company share the draw held; the company gets the rest
+pub fn deliver_random_draw_instruction<'info>(
+    ctx: Context<'_, '_, 'info, 'info, DeliverRandomDraw<'info>>,
+) -> Result<()> {
+    let random_draw = &ctx.accounts.random_draw;
+    let campaign = &ctx.accounts.campaign;
+    // Pending draws are the last pending_draws sequences sold, as random campaigns sell one NFT a sale
+    let oldest_pending = campaign
+        .total_mints
+        .checked_sub(campaign.pending_draws)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    require!(random_draw.sequence == oldest_pending, CustomError::DrawOutOfOrder);
+
+    let seed = draw_seed(
+        &ctx.accounts.slot_hashes,
+        random_draw.draw_slot,
+        &random_draw.buyer,
+        random_draw.sequence,
+    )?;
+    let inventory = &mut ctx.accounts.inventory;
+    let inventory_len = inventory.mints.len();
+    let index = draw_index(&seed.seed, inventory_len)?;
+    require_keys_eq!(
+        inventory.mints[index],
+        ctx.accounts.nft_mint.key(),
+        CustomError::InvalidRandomDraw
+    );
+    inventory.mints.swap_remove(index);
+    // Listing already checked it, but grouped campaigns only ever hand out their own members
+    require_group_member(&ctx.accounts.campaign, &ctx.accounts.nft_mint.to_account_info())?;
+
+    let random_draw = &ctx.accounts.random_draw;
+    let royalty = load_royalty(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata)?;
+    let royalty_amounts = match &royalty {
+        Some(royalty) => royalty.creator_amounts(random_draw.sale_amount)?,
+        None => Vec::new(),
+    };
+    check_creator_accounts(&[royalty], ctx.remaining_accounts, None)?;
+
+    // Listing only takes NFTs whose royalties fit in the company share, as a sale would
+    let royalty_total = royalty_amounts
+        .iter()
+        .try_fold(0u64, |total, amount| total.checked_add(*amount))
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let company_amount = random_draw
+        .held_amount
+        .checked_sub(royalty_total)
+        .ok_or(CustomError::RoyaltyExceedsProceeds)?;
+
+    let random_draw_info = random_draw.to_account_info();
+    for (creator, amount) in ctx.remaining_accounts.iter().zip(royalty_amounts) {
+        fund_rent_shortfall(
+            &ctx.accounts.payer.to_account_info(),
+            creator,
+            &ctx.accounts.system_program.to_account_info(),
+            amount,
+        )?;
+        random_draw_info.sub_lamports(amount)?;
+        creator.add_lamports(amount)?;
+    }
+    random_draw_info.sub_lamports(company_amount)?;
+    ctx.accounts.company.add_lamports(company_amount)?;
+
+    let campaign_key = ctx.accounts.campaign.key();
+    let escrow_seeds: &[&[u8]] = &[
+        b"nft_escrow",
+        campaign_key.as_ref(),
+        &[ctx.bumps.nft_escrow],
+    ];
+    transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.escrow_pda_nft_token_account.to_account_info(),
+                mint: ctx.accounts.nft_mint.to_account_info(),
+                to: ctx.accounts.recipient_token_account.to_account_info(),
+                authority: ctx.accounts.nft_escrow.to_account_info(),
+            },
+            &[escrow_seeds],
+        ),
+        1,
+        ctx.accounts.nft_mint.decimals,
+    )?;
+
+    let campaign = &mut ctx.accounts.campaign;
+    campaign.pending_draws = campaign
+        .pending_draws
+        .checked_sub(1)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    let random_draw = &ctx.accounts.random_draw;
+    emit!(RandomDrawEvent {
+        campaign: campaign_key,
+        buyer: random_draw.buyer,
+        recipient: random_draw.recipient,
+        sequence: random_draw.sequence,
+        draw_slot: random_draw.draw_slot,
+        slot: seed.slot,
+        slot_hash: seed.slot_hash,
+        seed: seed.seed,
+        inventory_len: inventory_len as u64,
+        index: index as u64,
+        nft_mint: ctx.accounts.nft_mint.key(),
+    });
+
+    Ok(())
+}
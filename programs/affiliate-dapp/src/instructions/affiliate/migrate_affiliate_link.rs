@@ -0,0 +1,98 @@
+use anchor_lang::{prelude::*, Discriminator};
+
+use crate::{error::CustomError, instructions::fund_affiliate_vault, state::*};
+
+#[derive(Accounts)]
+pub struct MigrateAffiliateLink<'info> {
+    #[account(mut)]
+    pub influencer: Signer<'info>,
+
+    #[account(
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    /// CHECK: Deserialized and validated as a LegacyAffiliateLink in the handler
+    #[account(
+        mut,
+        seeds = [b"affiliate_link", influencer.key().as_ref(), campaign.name.as_bytes()],
+        bump,
+    )]
+    pub legacy_affiliate_link: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = influencer,
+        space = 8 + AffiliateLink::INIT_SPACE,
+        seeds = [b"affiliate_link", influencer.key().as_ref(), campaign.key().as_ref()],
+        bump,
+    )]
+    pub affiliate_link: Account<'info, AffiliateLink>,
+
+    // Lamport-only PDA that collects this link's commissions
+    #[account(
+        mut,
+        seeds = [b"affiliate_vault", affiliate_link.key().as_ref()],
+        bump,
+    )]
+    pub affiliate_vault: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when a name-keyed affiliate link moves to its campaign-keyed address
+#[event]
+pub struct AffiliateLinkMigratedEvent {
+    pub legacy_affiliate_link: Pubkey,
+    pub affiliate_link: Pubkey,
+    pub campaign: Pubkey,
+    pub influencer: Pubkey,
+}
+
+pub fn migrate_affiliate_link_instruction(ctx: Context<MigrateAffiliateLink>) -> Result<()> {
+    let legacy: LegacyAffiliateLink = load_legacy_account(
+        &ctx.accounts.legacy_affiliate_link.to_account_info(),
+        AffiliateLink::DISCRIMINATOR,
+    )?;
+    require_keys_eq!(legacy.influencer, ctx.accounts.influencer.key(), CustomError::InvalidInfluencer);
+
+    // The link must point at the name-keyed campaign this campaign was migrated from, so a
+    // same-named campaign of another company can't take over its stats
+    require_keys_eq!(
+        legacy.campaign,
+        ctx.accounts.campaign.legacy_campaign,
+        CustomError::InvalidLegacyAccount
+    );
+
+    let affiliate_link = &mut ctx.accounts.affiliate_link;
+    affiliate_link.campaign = ctx.accounts.campaign.key();
+    affiliate_link.influencer = legacy.influencer;
+    affiliate_link.mints_count = legacy.mints_count;
+    affiliate_link.earnings = legacy.earnings;
+    // Legacy commissions were pushed straight to the influencer
+    affiliate_link.claimed_earnings = legacy.earnings;
+    affiliate_link.unclaimed_earnings = 0;
+    affiliate_link.created_at = legacy.created_at;
+    affiliate_link.payment_mint = Pubkey::default();
+
+    fund_affiliate_vault(
+        &ctx.accounts.influencer.to_account_info(),
+        &ctx.accounts.affiliate_vault.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    close_legacy_account(
+        &ctx.accounts.legacy_affiliate_link.to_account_info(),
+        &ctx.accounts.influencer.to_account_info(),
+    )?;
+
+    emit!(AffiliateLinkMigratedEvent {
+        legacy_affiliate_link: ctx.accounts.legacy_affiliate_link.key(),
+        affiliate_link: affiliate_link.key(),
+        campaign: affiliate_link.campaign,
+        influencer: affiliate_link.influencer,
+    });
+
+    Ok(())
+}
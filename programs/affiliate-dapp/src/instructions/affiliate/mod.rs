@@ -1,4 +1,18 @@
 pub use create_affiliate_link::*;
 pub mod create_affiliate_link;
 pub use process_affiliate_link::*;
-pub mod process_affiliate_link;
\ No newline at end of file
+pub mod process_affiliate_link;
+pub use migrate_affiliate_link::*;
+pub mod migrate_affiliate_link;
+pub use claim_earnings::*;
+pub mod claim_earnings;
+pub use process_affiliate_fresh_mint::*;
+pub mod process_affiliate_fresh_mint;
+pub use purchase_direct::*;
+pub mod purchase_direct;
+pub use deliver_random_draw::*;
+pub mod deliver_random_draw;
+pub use renew_random_draw::*;
+pub mod renew_random_draw;
+pub use quote_price::*;
+pub mod quote_price;
@@ -0,0 +1,415 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{create_idempotent, AssociatedToken, Create},
+    token_2022::{
+        self, initialize_mint2, mint_to, set_authority,
+        spl_token_2022::{
+            extension::ExtensionType, instruction::AuthorityType, state::Mint as MintState,
+        },
+        InitializeMint2, MintTo, SetAuthority, Token2022,
+    },
+    token_interface::{
+        group_member_pointer_initialize, metadata_pointer_initialize,
+        spl_pod::optional_keys::OptionalNonZeroPubkey,
+        spl_token_metadata_interface::state::TokenMetadata, token_metadata_initialize,
+        GroupMemberPointerInitialize, MetadataPointerInitialize, TokenMetadataInitialize,
+    },
+};
+
+use crate::{error::CustomError, group::*, payment::*, state::*};
+
+#[derive(Accounts)]
+#[instruction(currency: Option<Pubkey>)]
+pub struct ProcessAffiliateFreshMint<'info> {
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        constraint = campaign.active @ CustomError::CampaignNotActive,
+        constraint = campaign.mode == CampaignMode::FreshMint @ CustomError::InvalidCampaignMode,
+    )]
+    pub campaign: Box<Account<'info, NFTCampaign>>,
+
+    #[account(
+        mut,
+        seeds = [b"affiliate_link", influencer.key().as_ref(), campaign.key().as_ref()],
+        bump
+    )]
+    pub affiliate_link: Account<'info, AffiliateLink>,
+
+    // Buyer, payer, company wallet and everything the payment goes through
+    pub sale: SaleAccounts<'info>,
+    /// CHECK: Wallet receiving the NFTs; the buyer itself unless the purchase is a gift
+    pub recipient: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in constraint
+    pub influencer: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"affiliate_vault", affiliate_link.key().as_ref()],
+        bump,
+    )]
+    pub affiliate_vault: SystemAccount<'info>,
+
+    /// CHECK: Mint and metadata authority of the campaign's NFTs
+    #[account(
+        seeds = [b"mint_authority", campaign.key().as_ref()],
+        bump,
+    )]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Created in the handler as the next NFT in the campaign sequence
+    #[account(
+        mut,
+        seeds = [b"fresh_mint", campaign.key().as_ref(), &campaign.fresh_mint_count.to_le_bytes()],
+        bump,
+    )]
+    pub nft_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Campaign group the new NFT joins; required when the campaign has one
+    #[account(mut, address = campaign.group_mint)]
+    pub group_mint: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Recipient ATA for the new mint, created in the handler by the associated token program
+    #[account(mut)]
+    pub recipient_token_account: UncheckedAccount<'info>,
+
+    // Where commissions in a price-table currency are booked
+    #[account(
+        init_if_needed,
+        payer = sale.payer,
+        space = 8 + AffiliateEarnings::INIT_SPACE,
+        seeds = [
+            b"affiliate_earnings",
+            affiliate_link.key().as_ref(),
+            currency.unwrap_or_default().as_ref(),
+        ],
+        bump,
+    )]
+    pub affiliate_earnings: Option<Box<Account<'info, AffiliateEarnings>>>,
+
+    /// CHECK: Affiliate vault's ATA of the payment mint, created in charge_sale; token sales only
+    #[account(mut)]
+    pub vault_payment_account: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when a purchase mints a new NFT for the campaign
+#[event]
+pub struct FreshNftMintedEvent {
+    pub campaign: Pubkey,
+    pub nft_mint: Pubkey,
+    pub buyer: Pubkey,
+    pub recipient: Pubkey,
+    pub sequence: u64,
+}
+
+// Each NFT after the first is passed in remaining_accounts as
+// [next fresh_mint PDA in sequence, recipient token account for it]
+const FRESH_NFT_ACCOUNTS: usize = 2;
+
+// `max_total_price` caps everything the buyer pays for all `quantity` NFTs, in the chosen currency
+pub fn process_affiliate_fresh_mint_instruction<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ProcessAffiliateFreshMint<'info>>,
+    currency: Option<Pubkey>,
+    max_total_price: u64,
+    quantity: u64,
+) -> Result<()> {
+    let campaign = &ctx.accounts.campaign;
+    let affiliate_link = &ctx.accounts.affiliate_link;
+
+    require!(quantity > 0, CustomError::InvalidAmount);
+    require!(
+        ctx.remaining_accounts.len() as u64
+            == (quantity - 1).saturating_mul(FRESH_NFT_ACCOUNTS as u64),
+        CustomError::InvalidRemainingAccounts
+    );
+    // Fail before any lamports move if the campaign can't mint that many more
+    require!(campaign.available_supply >= quantity, CustomError::SoldOut);
+    require!(
+        ctx.accounts.influencer.key() == affiliate_link.influencer,
+        CustomError::InvalidInfluencer
+    );
+
+    // Fresh mints carry no Metaplex royalties
+    let Charge { currency, split, .. } = charge_sale(
+        &ctx.accounts.sale,
+        campaign,
+        &ctx.accounts.associated_token_program.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &[],
+        SaleOrder {
+            currency,
+            max_total_price,
+            quantity,
+            commission_bps: campaign.commission_bps,
+            royalties: &[],
+            affiliate_vault: Some(ctx.accounts.affiliate_vault.to_account_info()),
+            vault_payment_account: ctx
+                .accounts
+                .vault_payment_account
+                .as_ref()
+                .map(|account| account.to_account_info()),
+            held_by: None,
+        },
+    )?;
+
+    let first_index = campaign.fresh_mint_count;
+    let mut nft_accounts = vec![(
+        ctx.accounts.nft_mint.to_account_info(),
+        ctx.accounts.recipient_token_account.to_account_info(),
+        ctx.bumps.nft_mint,
+    )];
+    for (offset, extra) in ctx.remaining_accounts.chunks(FRESH_NFT_ACCOUNTS).enumerate() {
+        let [nft_mint, recipient_token_account] = extra else {
+            return err!(CustomError::InvalidRemainingAccounts);
+        };
+        let index = first_index + 1 + offset as u64;
+        let (expected_mint, bump) = Pubkey::find_program_address(
+            &[b"fresh_mint", campaign.key().as_ref(), &index.to_le_bytes()],
+            ctx.program_id,
+        );
+        require_keys_eq!(nft_mint.key(), expected_mint, CustomError::InvalidRemainingAccounts);
+        nft_accounts.push((nft_mint.clone(), recipient_token_account.clone(), bump));
+    }
+
+    for (offset, (nft_mint, recipient_token_account, nft_mint_bump)) in nft_accounts.into_iter().enumerate() {
+        mint_fresh_nft(
+            &FreshMintAccounts {
+                campaign,
+                buyer: ctx.accounts.sale.buyer.to_account_info(),
+                payer: ctx.accounts.sale.payer.to_account_info(),
+                recipient: ctx.accounts.recipient.to_account_info(),
+                recipient_token_account,
+                nft_mint,
+                group_mint: ctx.accounts.group_mint.as_ref().map(|group| group.to_account_info()),
+                mint_authority: ctx.accounts.mint_authority.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                associated_token_program: ctx.accounts.associated_token_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            first_index + offset as u64,
+            nft_mint_bump,
+            ctx.bumps.mint_authority,
+        )?;
+    }
+
+    // Update stats
+    let campaign = &mut ctx.accounts.campaign;
+    campaign.fresh_mint_count += quantity;
+    campaign.total_mints += quantity;
+    campaign.available_supply -= quantity;
+    let affiliate_link = &mut ctx.accounts.affiliate_link;
+    let affiliate_link_key = affiliate_link.key();
+    affiliate_link.mints_count += quantity;
+    affiliate_link.record_commission(
+        affiliate_link_key,
+        currency,
+        ctx.accounts.affiliate_earnings.as_deref_mut().map(|earnings| &mut **earnings),
+        split.commission_amount,
+    )?;
+
+    Ok(())
+}
+
+// Accounts needed to mint the next NFT of a fresh-mint campaign
+pub struct FreshMintAccounts<'a, 'info> {
+    pub campaign: &'a Account<'info, NFTCampaign>,
+    pub buyer: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub recipient: AccountInfo<'info>,
+    pub recipient_token_account: AccountInfo<'info>,
+    pub nft_mint: AccountInfo<'info>,
+    pub group_mint: Option<AccountInfo<'info>>,
+    pub mint_authority: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub associated_token_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+}
+
+// Creates the [b"fresh_mint", campaign, index] mint with on-mint metadata, adds it to the
+// campaign group if there is one, mints one token to the recipient and removes the mint
+// authority so the supply is fixed at one.
+pub fn mint_fresh_nft(
+    accounts: &FreshMintAccounts,
+    index: u64,
+    nft_mint_bump: u8,
+    mint_authority_bump: u8,
+) -> Result<()> {
+    let campaign = accounts.campaign;
+    let campaign_key = campaign.key();
+    let index_bytes = index.to_le_bytes();
+    let sequence = index + 1;
+
+    let mint_seeds: &[&[u8]] = &[
+        b"fresh_mint",
+        campaign_key.as_ref(),
+        &index_bytes,
+        &[nft_mint_bump],
+    ];
+    let authority_seeds: &[&[u8]] = &[
+        b"mint_authority",
+        campaign_key.as_ref(),
+        &[mint_authority_bump],
+    ];
+
+    let group_mint = if campaign.group_mint == Pubkey::default() {
+        None
+    } else {
+        Some(accounts.group_mint.clone().ok_or(CustomError::MissingGroupMint)?)
+    };
+
+    let name = format!("{} #{}", campaign.name, sequence);
+    let symbol = String::new();
+    let uri = format!("{}{}", campaign.base_uri, sequence);
+
+    // Allocate the mint with its pointer extensions; the metadata TLV is appended
+    // by token_metadata_initialize, so pre-fund the rent for it as well
+    let mut extensions = vec![ExtensionType::MetadataPointer];
+    if group_mint.is_some() {
+        extensions.push(ExtensionType::GroupMemberPointer);
+    }
+    let mint_len = ExtensionType::try_calculate_account_len::<MintState>(&extensions)?;
+    let metadata = TokenMetadata {
+        update_authority: OptionalNonZeroPubkey::try_from(Some(accounts.mint_authority.key()))?,
+        mint: accounts.nft_mint.key(),
+        name: name.clone(),
+        symbol: symbol.clone(),
+        uri: uri.clone(),
+        additional_metadata: vec![],
+    };
+    let lamports = Rent::get()?.minimum_balance(mint_len + metadata.tlv_size_of()?);
+
+    create_pda_account(
+        &accounts.payer,
+        &accounts.nft_mint,
+        &accounts.system_program,
+        lamports,
+        mint_len,
+        &token_2022::ID,
+        mint_seeds,
+    )?;
+
+    metadata_pointer_initialize(
+        CpiContext::new(
+            accounts.token_program.clone(),
+            MetadataPointerInitialize {
+                token_program_id: accounts.token_program.clone(),
+                mint: accounts.nft_mint.clone(),
+            },
+        ),
+        Some(accounts.mint_authority.key()),
+        Some(accounts.nft_mint.key()),
+    )?;
+
+    if group_mint.is_some() {
+        group_member_pointer_initialize(
+            CpiContext::new(
+                accounts.token_program.clone(),
+                GroupMemberPointerInitialize {
+                    token_program_id: accounts.token_program.clone(),
+                    mint: accounts.nft_mint.clone(),
+                },
+            ),
+            Some(accounts.mint_authority.key()),
+            Some(accounts.nft_mint.key()),
+        )?;
+    }
+
+    initialize_mint2(
+        CpiContext::new(
+            accounts.token_program.clone(),
+            InitializeMint2 {
+                mint: accounts.nft_mint.clone(),
+            },
+        ),
+        0,
+        &accounts.mint_authority.key(),
+        None,
+    )?;
+
+    token_metadata_initialize(
+        CpiContext::new_with_signer(
+            accounts.token_program.clone(),
+            TokenMetadataInitialize {
+                token_program_id: accounts.token_program.clone(),
+                metadata: accounts.nft_mint.clone(),
+                update_authority: accounts.mint_authority.clone(),
+                mint_authority: accounts.mint_authority.clone(),
+                mint: accounts.nft_mint.clone(),
+            },
+            &[authority_seeds],
+        ),
+        name,
+        symbol,
+        uri,
+    )?;
+
+    // The mint authority PDA is both the new mint's authority and the group update authority
+    if let Some(group_mint) = group_mint {
+        register_group_member(
+            &GroupMemberAccounts {
+                payer: accounts.payer.clone(),
+                member_mint: accounts.nft_mint.clone(),
+                member_mint_authority: accounts.mint_authority.clone(),
+                group_mint,
+                group_update_authority: accounts.mint_authority.clone(),
+                token_program: accounts.token_program.clone(),
+                system_program: accounts.system_program.clone(),
+            },
+            &[authority_seeds],
+        )?;
+    }
+
+    create_idempotent(CpiContext::new(
+        accounts.associated_token_program.clone(),
+        Create {
+            payer: accounts.payer.clone(),
+            associated_token: accounts.recipient_token_account.clone(),
+            authority: accounts.recipient.clone(),
+            mint: accounts.nft_mint.clone(),
+            system_program: accounts.system_program.clone(),
+            token_program: accounts.token_program.clone(),
+        },
+    ))?;
+
+    mint_to(
+        CpiContext::new_with_signer(
+            accounts.token_program.clone(),
+            MintTo {
+                mint: accounts.nft_mint.clone(),
+                to: accounts.recipient_token_account.clone(),
+                authority: accounts.mint_authority.clone(),
+            },
+            &[authority_seeds],
+        ),
+        1,
+    )?;
+
+    set_authority(
+        CpiContext::new_with_signer(
+            accounts.token_program.clone(),
+            SetAuthority {
+                current_authority: accounts.mint_authority.clone(),
+                account_or_mint: accounts.nft_mint.clone(),
+            },
+            &[authority_seeds],
+        ),
+        AuthorityType::MintTokens,
+        None,
+    )?;
+
+    emit!(FreshNftMintedEvent {
+        campaign: campaign_key,
+        nft_mint: accounts.nft_mint.key(),
+        buyer: accounts.buyer.key(),
+        recipient: accounts.recipient.key(),
+        sequence,
+    });
+
+    Ok(())
+}
@@ -1,47 +1,54 @@
-use anchor_lang::{prelude::*, solana_program::program::invoke};
+use anchor_lang::prelude::*;
 
 use anchor_spl::{
-    associated_token::AssociatedToken,
-    
+    associated_token::{
+        create_idempotent, get_associated_token_address_with_program_id, AssociatedToken, Create,
+    },
+
     token_interface::{
-        spl_token_2022::ID as TOKEN_2022_PROGRAM_ID,
         Mint, TokenAccount, TokenInterface,TransferChecked, transfer_checked
     },
 };
-use crate::{error::CustomError, state::*};
+use crate::{error::CustomError, group::*, payment::*, metadata::*, state::*};
 
 #[derive(Accounts)]
-#[instruction(campaign_name: String, influencer: Pubkey)]
+#[instruction(currency: Option<Pubkey>)]
 pub struct ProcessAffiliateMint<'info> {
     #[account(
         mut,
-        seeds = [b"nft_campaign", campaign_name.as_bytes()],
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
         bump,
-        constraint = campaign.active == true
+        constraint = campaign.active @ CustomError::CampaignNotActive,
+        constraint = campaign.mode == CampaignMode::Escrow @ CustomError::InvalidCampaignMode,
     )]
     pub campaign: Account<'info, NFTCampaign>,
 
     #[account(
         mut,
-        seeds = [b"affiliate_link", influencer.key().as_ref(), campaign_name.as_bytes()],
+        seeds = [b"affiliate_link", influencer.key().as_ref(), campaign.key().as_ref()],
         bump
     )]
     pub affiliate_link: Account<'info, AffiliateLink>,
 
-    #[account(mut)]
-    pub buyer: Signer<'info>,
+    // Buyer, payer, company wallet and everything the payment goes through
+    pub sale: SaleAccounts<'info>,
+    /// CHECK: Wallet receiving the NFTs; the buyer itself unless the purchase is a gift
+    pub recipient: UncheckedAccount<'info>,
 
-    #[account(mut)]
-    /// CHECK: Validated in constraint
-    pub owner: UncheckedAccount<'info>,
      /// CHECK: Validated in constraint
-    #[account(mut)]
     pub influencer: AccountInfo<'info>,
-    
+
     #[account(
-        address = campaign.nft_mint
+        mut,
+        seeds = [b"affiliate_vault", affiliate_link.key().as_ref()],
+        bump,
     )]
-    pub nft_mint: InterfaceAccount<'info, Mint>,
+    pub affiliate_vault: SystemAccount<'info>,
+    
+    // The escrowed NFT the buyer picks; left out when the campaign draws at random
+    pub nft_mint: Option<InterfaceAccount<'info, Mint>>,
+    /// CHECK: Metaplex metadata PDA of nft_mint, empty if it has none; checked in load_royalty
+    pub nft_metadata: Option<UncheckedAccount<'info>>,
     
      /// CHECK: Validated in constraint
       #[account(
@@ -53,21 +60,13 @@ pub struct ProcessAffiliateMint<'info> {
     pub nft_escrow: UncheckedAccount<'info>,
     #[account(
         init_if_needed,
-        payer = buyer,
+        payer = sale.payer,
         associated_token::mint = nft_mint,
-        associated_token::authority = buyer,
+        associated_token::authority = recipient,
         associated_token::token_program = token_program
     )]
-    pub buyer_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub recipient_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
     
-    #[account(
-        mut,
-        associated_token::mint = campaign.nft_mint,
-        associated_token::authority = owner.key(),
-        associated_token::token_program = token_program,
-        owner = TOKEN_2022_PROGRAM_ID,
-    )]
-    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
  #[account(
         mut,
         associated_token::mint = nft_mint,
@@ -75,14 +74,40 @@ pub struct ProcessAffiliateMint<'info> {
         associated_token::token_program = token_program,
     )]
 
-   pub escrow_pda_nft_token_account: InterfaceAccount<'info, TokenAccount>,
+   pub escrow_pda_nft_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // Random selection only: the record deliver_random_draw draws the NFT for
+    #[account(
+        init,
+        payer = sale.payer,
+        space = 8 + RandomDraw::INIT_SPACE,
+        seeds = [
+            b"random_draw",
+            campaign.key().as_ref(),
+            campaign.total_mints.to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub random_draw: Option<Box<Account<'info, RandomDraw>>>,
+    // Where commissions in a price-table currency are booked
     #[account(
-        seeds = [b"marketplace_authority"],
+        init_if_needed,
+        payer = sale.payer,
+        space = 8 + AffiliateEarnings::INIT_SPACE,
+        seeds = [
+            b"affiliate_earnings",
+            affiliate_link.key().as_ref(),
+            currency.unwrap_or_default().as_ref(),
+        ],
         bump,
     )]
-    pub marketplace_authority: SystemAccount<'info>,
-    
-    #[account(address = TOKEN_2022_PROGRAM_ID)]
+    pub affiliate_earnings: Option<Box<Account<'info, AffiliateEarnings>>>,
+
+    /// CHECK: Affiliate vault's ATA of the payment mint, created in charge_sale; token sales only
+    #[account(mut)]
+    pub vault_payment_account: Option<UncheckedAccount<'info>>,
+
+    #[account(address = campaign.token_program @ CustomError::InvalidTokenProgram)]
     pub token_program: Interface<'info, TokenInterface>,
     
     pub associated_token_program: Program<'info, AssociatedToken>,
@@ -90,93 +115,270 @@ pub struct ProcessAffiliateMint<'info> {
 }
 
 
-pub fn process_affiliate_mint_instruction(ctx: Context<ProcessAffiliateMint>, _campaign_name:String, _influencer:Pubkey) -> Result<()> {
-    let campaign = &mut ctx.accounts.campaign;
-    let affiliate_link = &mut ctx.accounts.affiliate_link;
-    let campaign_key = campaign.key().clone();
-    // Calculate commission amount with proper overflow checks
-    let commission_amount = (campaign.mint_price as u64)
-        .checked_mul(campaign.commission_percentage as u64)
-        .ok_or(ProgramError::ArithmeticOverflow)?
-        .checked_div(100)
-        .ok_or(ProgramError::ArithmeticOverflow)?;
+// Event emitted when NFTs are sold through an affiliate link
+#[event]
+pub struct AffiliateSaleEvent {
+    pub campaign: Pubkey,
+    pub affiliate_link: Pubkey,
+    pub nft_mint: Pubkey,
+    pub buyer: Pubkey,
+    pub recipient: Pubkey,
+    pub quantity: u64,
+    pub currency: Pubkey,
+    pub commission_amount: u64,
+}
 
-    // Calculate amount going to the NFT project
-    let project_amount = campaign.mint_price
-        .checked_sub(commission_amount)
-        .ok_or(ProgramError::ArithmeticOverflow)?;
+// Each NFT after the first is passed in remaining_accounts as
+// [nft_mint, campaign escrow token account, recipient token account, metadata PDA],
+// followed by the accounts of every NFT's royalty creators (see check_creator_accounts)
+const ESCROWED_NFT_ACCOUNTS: usize = 4;
+
+// `max_total_price` caps everything the buyer pays for all `quantity` NFTs, in the chosen currency
+pub fn process_affiliate_mint_instruction<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ProcessAffiliateMint<'info>>,
+    currency: Option<Pubkey>,
+    max_total_price: u64,
+    quantity: u64,
+) -> Result<()> {
+    let campaign = &ctx.accounts.campaign;
+    let affiliate_link = &ctx.accounts.affiliate_link;
+    let campaign_key = campaign.key();
+    let random = campaign.selection_mode == SelectionMode::Random;
+
+    require!(quantity > 0, CustomError::InvalidAmount);
+    // Each random draw is delivered on its own through deliver_random_draw
+    require!(!random || quantity == 1, CustomError::InvalidAmount);
+    let extra_nft_accounts_len = usize::try_from(quantity - 1)
+        .ok()
+        .and_then(|extra| extra.checked_mul(ESCROWED_NFT_ACCOUNTS))
+        .filter(|len| *len <= ctx.remaining_accounts.len())
+        .ok_or(CustomError::InvalidRemainingAccounts)?;
+    let (extra_nft_accounts, creator_accounts) =
+        ctx.remaining_accounts.split_at(extra_nft_accounts_len);
+    // Fail before any lamports move if there is not enough left to sell
+    let mut royalties = Vec::new();
+    if !random {
+        let (nft_mint, escrow_token_account, _) = chosen_nft_accounts(ctx.accounts)?;
+        require!(escrow_token_account.amount > 0, CustomError::SoldOut);
+        // Grouped campaigns only sell NFTs registered in their group
+        require_group_member(campaign, &nft_mint.to_account_info())?;
+        let nft_metadata = ctx.accounts.nft_metadata.as_ref().ok_or(CustomError::MissingNftAccounts)?;
+        royalties.push(load_royalty(&nft_mint.key(), nft_metadata)?);
+        for nft_accounts in extra_nft_accounts.chunks(ESCROWED_NFT_ACCOUNTS) {
+            let [nft_mint, _, _, nft_metadata] = nft_accounts else {
+                return err!(CustomError::InvalidRemainingAccounts);
+            };
+            royalties.push(load_royalty(nft_mint.key, nft_metadata)?);
+        }
+    }
+    require!(campaign.available_supply >= quantity, CustomError::SoldOut);
 
-    // 1. Transfer commission to the influencer
     // Verify influencer account matches the one in the affiliate link
     require!(
         ctx.accounts.influencer.key() == affiliate_link.influencer,
         CustomError::InvalidInfluencer
     );
-    
-    let transfer_to_influencer_ix = anchor_lang::solana_program::system_instruction::transfer(
-        &ctx.accounts.buyer.key(),
-        &affiliate_link.influencer,
-        commission_amount,
-    );
 
-    invoke(
-        &transfer_to_influencer_ix,
-        &[
-            ctx.accounts.buyer.to_account_info(),
-            ctx.accounts.influencer.to_account_info(),
-            ctx.accounts.system_program.to_account_info(),
-        ],
+    // Each NFT pays its own creators. A random draw's NFT isn't known yet, so the
+    // company's share waits in the draw record and royalties are paid on delivery.
+    let Charge { currency, price, split } = charge_sale(
+        &ctx.accounts.sale,
+        campaign,
+        &ctx.accounts.associated_token_program.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        creator_accounts,
+        SaleOrder {
+            currency,
+            max_total_price,
+            quantity,
+            commission_bps: campaign.commission_bps,
+            royalties: &royalties,
+            affiliate_vault: Some(ctx.accounts.affiliate_vault.to_account_info()),
+            vault_payment_account: ctx
+                .accounts
+                .vault_payment_account
+                .as_ref()
+                .map(|account| account.to_account_info()),
+            held_by: if random {
+                let random_draw = ctx.accounts.random_draw.as_ref();
+                Some(random_draw.ok_or(CustomError::MissingRandomDrawAccounts)?.to_account_info())
+            } else {
+                None
+            },
+        },
     )?;
 
-    // 2. Transfer remaining amount to the NFT project
-    // Verify owner account matches the company in the campaign
-    require!(
-        ctx.accounts.owner.key() == campaign.company,
-        CustomError::InvalidAccountOwner
-    );
-    
-    let transfer_to_project_ix = anchor_lang::solana_program::system_instruction::transfer(
-        &ctx.accounts.buyer.key(),
-        &campaign.company,
-        project_amount,
-    );
+    // A random purchase doesn't know its NFT yet, so its sale event names none
+    let sold_mint = if random {
+        let sale_amount = price
+            .checked_sub(split.protocol_fee)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        record_random_draw(ctx.accounts, ctx.bumps.random_draw, sale_amount, split.project_amount)?;
+        Pubkey::default()
+    } else {
+        // Get the seeds for signing as the escrow PDA
+        let escrow_seeds = [
+            b"nft_escrow",
+            campaign_key.as_ref(),
+            &[ctx.bumps.nft_escrow],
+        ];
+        let escrow_seeds_ref = [&escrow_seeds[..]];
 
-    invoke(
-        &transfer_to_project_ix,
-        &[
-            ctx.accounts.buyer.to_account_info(),
-            ctx.accounts.owner.to_account_info(),
-            ctx.accounts.system_program.to_account_info(),
-        ],
-    )?;
+        let (nft_mint, escrow_token_account, recipient_token_account) =
+            chosen_nft_accounts(ctx.accounts)?;
+        let transfer_accounts = TransferChecked {
+            from: escrow_token_account.to_account_info(),
+            mint: nft_mint.to_account_info(),
+            to: recipient_token_account.to_account_info(),
+            authority: ctx.accounts.nft_escrow.to_account_info()
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_accounts,
+            &escrow_seeds_ref
+        );
 
-    // Get the seeds for signing as the escrow PDA
-    let escrow_seeds = [
-        b"nft_escrow",
-        campaign_key.as_ref(),
-        &[ctx.bumps.nft_escrow],
-    ];
-    let escrow_seeds_ref = [&escrow_seeds[..]];
-
-    let transfer_accounts = TransferChecked {
-        from: ctx.accounts.escrow_pda_nft_token_account.to_account_info(),
-        mint: ctx.accounts.nft_mint.to_account_info(),
-        to: ctx.accounts.buyer_token_account.to_account_info(),
-        authority: ctx.accounts.nft_escrow.to_account_info()
+        transfer_checked(cpi_ctx, 1, nft_mint.decimals)?;
+        let nft_mint_key = nft_mint.key();
+
+        for nft_accounts in extra_nft_accounts.chunks(ESCROWED_NFT_ACCOUNTS) {
+            transfer_escrowed_nft(ctx.accounts, nft_accounts, &escrow_seeds_ref)?;
+        }
+        nft_mint_key
     };
-   
-    let cpi_ctx = CpiContext::new_with_signer(
-        ctx.accounts.token_program.to_account_info(), 
-        transfer_accounts,
-        &escrow_seeds_ref
-    );
-    
-    transfer_checked(cpi_ctx, 1, ctx.accounts.nft_mint.decimals)?;
 
     // Update stats
-    campaign.total_mints += 1;
-    affiliate_link.mints_count += 1;
-    affiliate_link.earnings += commission_amount;
+    let campaign = &mut ctx.accounts.campaign;
+    campaign.total_mints += quantity;
+    campaign.available_supply -= quantity;
+    let affiliate_link = &mut ctx.accounts.affiliate_link;
+    let affiliate_link_key = affiliate_link.key();
+    affiliate_link.mints_count += quantity;
+    affiliate_link.record_commission(
+        affiliate_link_key,
+        currency,
+        ctx.accounts.affiliate_earnings.as_deref_mut().map(|earnings| &mut **earnings),
+        split.commission_amount,
+    )?;
+
+    emit!(AffiliateSaleEvent {
+        campaign: campaign_key,
+        affiliate_link: affiliate_link_key,
+        nft_mint: sold_mint,
+        buyer: ctx.accounts.sale.buyer.key(),
+        recipient: ctx.accounts.recipient.key(),
+        quantity,
+        currency,
+        commission_amount: split.commission_amount,
+    });
+
+    Ok(())
+}
+
+// The NFT a buyer-choice purchase names, with its escrow and recipient token accounts
+fn chosen_nft_accounts<'a, 'info>(
+    accounts: &'a ProcessAffiliateMint<'info>,
+) -> Result<(
+    &'a InterfaceAccount<'info, Mint>,
+    &'a InterfaceAccount<'info, TokenAccount>,
+    &'a InterfaceAccount<'info, TokenAccount>,
+)> {
+    match (
+        &accounts.nft_mint,
+        &accounts.escrow_pda_nft_token_account,
+        &accounts.recipient_token_account,
+    ) {
+        (Some(nft_mint), Some(escrow_token_account), Some(recipient_token_account)) => {
+            Ok((nft_mint, escrow_token_account, recipient_token_account))
+        }
+        _ => err!(CustomError::MissingNftAccounts),
+    }
+}
+
+// Records a random purchase for deliver_random_draw. The NFT is drawn there from the
+// hash of a slot after this one, which nobody can know when sending the purchase.
+fn record_random_draw(
+    accounts: &mut ProcessAffiliateMint,
+    random_draw_bump: Option<u8>,
+    sale_amount: u64,
+    held_amount: u64,
+) -> Result<()> {
+    let campaign_key = accounts.campaign.key();
+    let (Some(random_draw), Some(bump)) = (accounts.random_draw.as_mut(), random_draw_bump) else {
+        return err!(CustomError::MissingRandomDrawAccounts);
+    };
+
+    random_draw.set_inner(RandomDraw {
+        campaign: campaign_key,
+        buyer: accounts.sale.buyer.key(),
+        recipient: accounts.recipient.key(),
+        rent_payer: accounts.sale.payer.key(),
+        sequence: accounts.campaign.total_mints,
+        draw_slot: Clock::get()?.slot,
+        sale_amount,
+        held_amount,
+        bump,
+    });
+    accounts.campaign.pending_draws = accounts
+        .campaign
+        .pending_draws
+        .checked_add(1)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// Moves one more escrowed NFT of a multi-NFT purchase to the recipient, creating its ATA if needed
+fn transfer_escrowed_nft<'info>(
+    accounts: &ProcessAffiliateMint<'info>,
+    nft_accounts: &'info [AccountInfo<'info>],
+    escrow_signer: &[&[&[u8]]],
+) -> Result<()> {
+    let [nft_mint, escrow_token_account, recipient_token_account, _] = nft_accounts else {
+        return err!(CustomError::InvalidRemainingAccounts);
+    };
+    require_keys_eq!(
+        *nft_mint.owner,
+        accounts.token_program.key(),
+        CustomError::InvalidTokenProgram
+    );
+    let mint = InterfaceAccount::<Mint>::try_from(nft_mint)?;
+    require_group_member(&accounts.campaign, nft_mint)?;
+    require_keys_eq!(
+        escrow_token_account.key(),
+        get_associated_token_address_with_program_id(
+            &accounts.nft_escrow.key(),
+            &nft_mint.key(),
+            &accounts.token_program.key(),
+        ),
+        CustomError::InvalidRemainingAccounts
+    );
+
+    create_idempotent(CpiContext::new(
+        accounts.associated_token_program.to_account_info(),
+        Create {
+            payer: accounts.sale.payer.to_account_info(),
+            associated_token: recipient_token_account.clone(),
+            authority: accounts.recipient.to_account_info(),
+            mint: nft_mint.clone(),
+            system_program: accounts.system_program.to_account_info(),
+            token_program: accounts.token_program.to_account_info(),
+        },
+    ))?;
+
+    transfer_checked(
+        CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: escrow_token_account.clone(),
+                mint: nft_mint.clone(),
+                to: recipient_token_account.clone(),
+                authority: accounts.nft_escrow.to_account_info(),
+            },
+            escrow_signer,
+        ),
+        1,
+        mint.decimals,
+    )
+}
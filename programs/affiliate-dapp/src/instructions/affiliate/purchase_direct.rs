@@ -0,0 +1,149 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+use crate::{error::CustomError, group::*, payment::*, metadata::*, state::*};
+
+#[derive(Accounts)]
+pub struct PurchaseDirect<'info> {
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        constraint = campaign.active @ CustomError::CampaignNotActive,
+        constraint = campaign.mode == CampaignMode::Escrow @ CustomError::InvalidCampaignMode,
+        // Random draws only go through affiliate purchases
+        constraint = campaign.selection_mode == SelectionMode::BuyerChoice @ CustomError::InvalidSelectionMode,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    // Buyer, payer, company wallet and everything the payment goes through
+    pub sale: SaleAccounts<'info>,
+    /// CHECK: Wallet receiving the NFT; the buyer itself unless the purchase is a gift
+    pub recipient: UncheckedAccount<'info>,
+
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: Metaplex metadata PDA of nft_mint, empty if it has none; checked in load_royalty
+    pub nft_metadata: UncheckedAccount<'info>,
+
+    /// CHECK: PDA that owns the campaign inventory
+    #[account(
+        seeds = [b"nft_escrow", campaign.key().as_ref()],
+        bump,
+    )]
+    pub nft_escrow: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = sale.payer,
+        associated_token::mint = nft_mint,
+        associated_token::authority = recipient,
+        associated_token::token_program = token_program
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = nft_escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_pda_nft_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = campaign.token_program @ CustomError::InvalidTokenProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when an NFT is sold without an affiliate link
+#[event]
+pub struct DirectSaleEvent {
+    pub campaign: Pubkey,
+    pub buyer: Pubkey,
+    pub recipient: Pubkey,
+    pub nft_mint: Pubkey,
+    pub currency: Pubkey,
+    pub price: u64,
+    pub protocol_fee: u64,
+}
+
+// Sells from the same escrow as affiliate purchases, but with no commission:
+// the protocol fee goes to the treasury, royalties to the creators passed in
+// remaining_accounts and everything else to the company.
+// `max_total_price` caps everything the buyer pays, in the chosen currency.
+pub fn purchase_direct_instruction<'info>(
+    ctx: Context<'_, '_, 'info, 'info, PurchaseDirect<'info>>,
+    currency: Option<Pubkey>,
+    max_total_price: u64,
+) -> Result<()> {
+    let campaign = &ctx.accounts.campaign;
+
+    // Fail before any lamports move if there is nothing left to sell
+    require!(
+        campaign.available_supply > 0 && ctx.accounts.escrow_pda_nft_token_account.amount > 0,
+        CustomError::SoldOut
+    );
+    // Grouped campaigns only sell NFTs registered in their group
+    require_group_member(campaign, &ctx.accounts.nft_mint.to_account_info())?;
+
+    let royalty = load_royalty(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata)?;
+    let Charge {
+        currency,
+        price,
+        split,
+    } = charge_sale(
+        &ctx.accounts.sale,
+        campaign,
+        &ctx.accounts.associated_token_program.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+        SaleOrder {
+            currency,
+            max_total_price,
+            quantity: 1,
+            commission_bps: 0,
+            royalties: &[royalty],
+            affiliate_vault: None,
+            vault_payment_account: None,
+            held_by: None,
+        },
+    )?;
+
+    let campaign_key = campaign.key();
+    let escrow_seeds: &[&[u8]] = &[
+        b"nft_escrow",
+        campaign_key.as_ref(),
+        &[ctx.bumps.nft_escrow],
+    ];
+    transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.escrow_pda_nft_token_account.to_account_info(),
+                mint: ctx.accounts.nft_mint.to_account_info(),
+                to: ctx.accounts.recipient_token_account.to_account_info(),
+                authority: ctx.accounts.nft_escrow.to_account_info(),
+            },
+            &[escrow_seeds],
+        ),
+        1,
+        ctx.accounts.nft_mint.decimals,
+    )?;
+
+    let campaign = &mut ctx.accounts.campaign;
+    campaign.direct_sales += 1;
+    campaign.available_supply -= 1;
+
+    emit!(DirectSaleEvent {
+        campaign: campaign_key,
+        buyer: ctx.accounts.sale.buyer.key(),
+        recipient: ctx.accounts.recipient.key(),
+        nft_mint: ctx.accounts.nft_mint.key(),
+        currency,
+        price,
+        protocol_fee: split.protocol_fee,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::CustomError, payment::resolve_price, state::*};
+
+#[derive(Accounts)]
+pub struct QuotePrice<'info> {
+    #[account(
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    #[account(
+        seeds = [b"price_table", campaign.key().as_ref()],
+        bump = price_table.bump,
+    )]
+    pub price_table: Option<Box<Account<'info, CampaignPriceTable>>>,
+    /// CHECK: Parsed as a price feed when the campaign is priced in USD
+    #[account(address = campaign.price_feed @ CustomError::InvalidPriceFeed)]
+    pub price_feed: Option<UncheckedAccount<'info>>,
+}
+
+// Price of one NFT if bought now in `currency`, returned through return data so
+// clients can simulate it to show the live auction price. Fee, commission and royalties
+// come out of this price; only Token-2022 transfer fees are charged on top.
+pub fn quote_price_instruction(ctx: Context<QuotePrice>, currency: Option<Pubkey>) -> Result<u64> {
+    let (_, price) = resolve_price(
+        &ctx.accounts.campaign,
+        ctx.accounts.price_table.as_deref().map(|table| &**table),
+        ctx.accounts.price_feed.as_ref().map(|feed| feed.as_ref()),
+        currency,
+    )?;
+    Ok(price)
+}
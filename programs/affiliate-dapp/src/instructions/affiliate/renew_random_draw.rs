@@ -0,0 +1,66 @@
+use anchor_lang::{prelude::*, solana_program::sysvar::slot_hashes};
+
+use crate::{error::CustomError, random::*, state::*};
+
+// Renews a draw nobody delivered while its slot hash was still in the window, so it draws
+// again from a new hash. Only the company may, or a buyer who dislikes the outcome could
+// let the draw expire and reroll it for free.
+#[derive(Accounts)]
+pub struct RenewRandomDraw<'info> {
+    pub company: Signer<'info>,
+
+    #[account(
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        has_one = company @ CustomError::Unauthorized,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"random_draw",
+            campaign.key().as_ref(),
+            random_draw.sequence.to_le_bytes().as_ref(),
+        ],
+        bump = random_draw.bump,
+        has_one = campaign @ CustomError::InvalidRandomDraw,
+    )]
+    pub random_draw: Account<'info, RandomDraw>,
+
+    /// CHECK: Address checked against the SlotHashes sysvar, read in find_draw_slot_hash
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+}
+
+// Event emitted when an expired draw moves to the hash of a later slot
+#[event]
+pub struct RandomDrawRenewedEvent {
+    pub campaign: Pubkey,
+    pub sequence: u64,
+    pub expired_slot: u64,
+    pub draw_slot: u64,
+}
+
+pub fn renew_random_draw_instruction(ctx: Context<RenewRandomDraw>) -> Result<()> {
+    let random_draw = &mut ctx.accounts.random_draw;
+    require!(
+        matches!(
+            find_draw_slot_hash(&ctx.accounts.slot_hashes, random_draw.draw_slot)?,
+            DrawSlotHash::Expired
+        ),
+        CustomError::DrawNotExpired
+    );
+
+    let expired_slot = random_draw.draw_slot;
+    random_draw.draw_slot = Clock::get()?.slot;
+
+    emit!(RandomDrawRenewedEvent {
+        campaign: ctx.accounts.campaign.key(),
+        sequence: random_draw.sequence,
+        expired_slot,
+        draw_slot: random_draw.draw_slot,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use anchor_lang::{prelude::*, solana_program::program_option::COption};
+use anchor_spl::{token_2022::Token2022, token_interface::Mint};
+
+use crate::{error::CustomError, group::*, state::*};
+
+#[derive(Accounts)]
+pub struct AddGroupMember<'info> {
+    #[account(mut)]
+    pub company: Signer<'info>,
+
+    #[account(
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        has_one = company @ CustomError::Unauthorized,
+        constraint = campaign.group_mint != Pubkey::default() @ CustomError::GroupNotCreated,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    /// CHECK: Update authority of the campaign group
+    #[account(
+        seeds = [b"mint_authority", campaign.key().as_ref()],
+        bump,
+    )]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the campaign and by the token program
+    #[account(mut, address = campaign.group_mint)]
+    pub group_mint: UncheckedAccount<'info>,
+
+    // Needs the group-member-pointer extension, and the company as mint authority or the
+    // campaign's mint_authority PDA if the campaign took it over with MintAuthorityLock::Campaign
+    #[account(
+        mut,
+        mint::token_program = token_program,
+        constraint = nft_mint.mint_authority == COption::Some(company.key())
+            || nft_mint.mint_authority == COption::Some(mint_authority.key()) @ CustomError::Unauthorized,
+    )]
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when an NFT joins the campaign group
+#[event]
+pub struct GroupMemberAddedEvent {
+    pub campaign: Pubkey,
+    pub group_mint: Pubkey,
+    pub nft_mint: Pubkey,
+}
+
+// Escrowed NFTs have to be members before they can be deposited or sold,
+// so companies register their own mints here first.
+pub fn add_group_member_instruction(ctx: Context<AddGroupMember>) -> Result<()> {
+    let campaign_key = ctx.accounts.campaign.key();
+    let authority_seeds: &[&[u8]] = &[
+        b"mint_authority",
+        campaign_key.as_ref(),
+        &[ctx.bumps.mint_authority],
+    ];
+    let company_is_authority =
+        ctx.accounts.nft_mint.mint_authority == COption::Some(ctx.accounts.company.key());
+    let member_mint_authority = if company_is_authority {
+        ctx.accounts.company.to_account_info()
+    } else {
+        ctx.accounts.mint_authority.to_account_info()
+    };
+
+    register_group_member(
+        &GroupMemberAccounts {
+            payer: ctx.accounts.company.to_account_info(),
+            member_mint: ctx.accounts.nft_mint.to_account_info(),
+            member_mint_authority,
+            group_mint: ctx.accounts.group_mint.to_account_info(),
+            group_update_authority: ctx.accounts.mint_authority.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        },
+        &[authority_seeds],
+    )?;
+
+    emit!(GroupMemberAddedEvent {
+        campaign: campaign_key,
+        group_mint: ctx.accounts.group_mint.key(),
+        nft_mint: ctx.accounts.nft_mint.key(),
+    });
+
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{error::CustomError, state::*};
+
+#[derive(Accounts)]
+pub struct CloseCampaign<'info> {
+    #[account(mut)]
+    pub company: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        has_one = company @ CustomError::Unauthorized,
+        close = company,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    // The escrow accounts are optional: fresh-mint campaigns never had any, and
+    // escrow campaigns may already have closed them through withdraw_inventory
+    #[account(address = campaign.nft_mint)]
+    pub nft_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: The PDA for the NFT escrow account
+    #[account(
+        seeds = [b"nft_escrow", campaign.key().as_ref()],
+        bump,
+    )]
+    pub nft_escrow: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = nft_escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_pda_nft_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = company,
+        associated_token::mint = nft_mint,
+        associated_token::authority = company,
+        associated_token::token_program = token_program,
+    )]
+    pub project_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // Closed along with the campaign when it draws NFTs at random
+    #[account(
+        mut,
+        seeds = [b"inventory", campaign.key().as_ref()],
+        bump = inventory.bump,
+        close = company,
+    )]
+    pub inventory: Option<Box<Account<'info, CampaignInventory>>>,
+
+    // Closed along with the campaign when it lists extra currencies
+    #[account(
+        mut,
+        seeds = [b"price_table", campaign.key().as_ref()],
+        bump = price_table.bump,
+        close = company,
+    )]
+    pub price_table: Option<Box<Account<'info, CampaignPriceTable>>>,
+
+    #[account(address = campaign.token_program @ CustomError::InvalidTokenProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when a campaign is closed and its escrow returned to the company
+#[event]
+pub struct CampaignClosedEvent {
+    pub campaign: Pubkey,
+    pub company: Pubkey,
+    pub returned_amount: u64,
+    pub closed_at: i64,
+}
+
+pub fn close_campaign_instruction(ctx: Context<CloseCampaign>) -> Result<()> {
+    // Drawn NFTs still in the escrow belong to their buyers
+    require!(ctx.accounts.campaign.pending_draws == 0, CustomError::PendingRandomDraws);
+    if ctx.accounts.campaign.selection_mode == SelectionMode::Random {
+        require!(ctx.accounts.inventory.is_some(), CustomError::MissingInventory);
+    }
+
+    let campaign_key = ctx.accounts.campaign.key();
+    let escrow_seeds = [
+        b"nft_escrow",
+        campaign_key.as_ref(),
+        &[ctx.bumps.nft_escrow],
+    ];
+    let escrow_seeds_ref = [&escrow_seeds[..]];
+
+    // Return whatever is still escrowed before closing the token account
+    let returned_amount = ctx
+        .accounts
+        .escrow_pda_nft_token_account
+        .as_ref()
+        .map_or(0, |escrow| escrow.amount);
+    // Other mints have to come out through withdraw_inventory first
+    if ctx.accounts.campaign.mode == CampaignMode::Escrow {
+        let remaining_supply = ctx
+            .accounts
+            .campaign
+            .available_supply
+            .saturating_sub(returned_amount);
+        require!(remaining_supply == 0, CustomError::InventoryNotEmpty);
+    }
+
+    if let (Some(nft_mint), Some(escrow_pda_nft_token_account), Some(project_token_account)) = (
+        &ctx.accounts.nft_mint,
+        &ctx.accounts.escrow_pda_nft_token_account,
+        &ctx.accounts.project_token_account,
+    ) {
+        if returned_amount > 0 {
+            let transfer_accounts = TransferChecked {
+                from: escrow_pda_nft_token_account.to_account_info(),
+                mint: nft_mint.to_account_info(),
+                to: project_token_account.to_account_info(),
+                authority: ctx.accounts.nft_escrow.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_accounts,
+                &escrow_seeds_ref,
+            );
+            transfer_checked(cpi_ctx, returned_amount, nft_mint.decimals)?;
+        }
+
+        // Reclaim the escrow ATA rent for the company
+        let close_accounts = CloseAccount {
+            account: escrow_pda_nft_token_account.to_account_info(),
+            destination: ctx.accounts.company.to_account_info(),
+            authority: ctx.accounts.nft_escrow.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            close_accounts,
+            &escrow_seeds_ref,
+        );
+        close_account(cpi_ctx)?;
+    } else {
+        require!(returned_amount == 0, CustomError::MissingEscrowAccounts);
+    }
+
+    emit!(CampaignClosedEvent {
+        campaign: campaign_key,
+        company: ctx.accounts.company.key(),
+        returned_amount,
+        closed_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
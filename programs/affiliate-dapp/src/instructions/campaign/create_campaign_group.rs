@@ -0,0 +1,162 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token_2022::{
+        self, initialize_mint2,
+        spl_token_2022::{extension::ExtensionType, state::Mint as MintState},
+        InitializeMint2, Token2022,
+    },
+    token_interface::{
+        group_pointer_initialize, token_group_initialize, GroupPointerInitialize, Mint,
+        TokenGroupInitialize,
+    },
+};
+
+use crate::{error::CustomError, state::*};
+
+#[derive(Accounts)]
+pub struct CreateCampaignGroup<'info> {
+    #[account(mut)]
+    pub company: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        has_one = company @ CustomError::Unauthorized,
+        constraint = campaign.group_mint == Pubkey::default() @ CustomError::GroupAlreadyCreated,
+        constraint = campaign.token_program == token_2022::ID @ CustomError::InvalidTokenProgram,
+        // A random inventory was checked against the campaign's group when listed, so it can't get one later
+        constraint = campaign.selection_mode == SelectionMode::BuyerChoice @ CustomError::InvalidSelectionMode,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    /// CHECK: Mint authority and update authority of the campaign group
+    #[account(
+        seeds = [b"mint_authority", campaign.key().as_ref()],
+        bump,
+    )]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Created in the handler as the campaign group mint
+    #[account(
+        mut,
+        seeds = [b"campaign_group", campaign.key().as_ref()],
+        bump,
+    )]
+    pub group_mint: UncheckedAccount<'info>,
+
+    // The NFT an escrow campaign was created with; required for escrow campaigns
+    #[account(address = campaign.nft_mint)]
+    pub nft_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when a campaign gets its Token-2022 group
+#[event]
+pub struct CampaignGroupCreatedEvent {
+    pub campaign: Pubkey,
+    pub group_mint: Pubkey,
+    pub max_size: u32,
+}
+
+// The group mint points at itself and is controlled by the campaign's mint authority PDA,
+// so only this program can add members. It never has any supply.
+pub fn create_campaign_group_instruction(ctx: Context<CreateCampaignGroup>, max_size: u32) -> Result<()> {
+    require!(max_size > 0, CustomError::InvalidAmount);
+    // Grouped campaigns only sell members, and joining takes the mint authority. A revoked
+    // one would leave the campaign's own NFT unsellable.
+    if ctx.accounts.campaign.mode == CampaignMode::Escrow {
+        require!(
+            ctx.accounts
+                .nft_mint
+                .as_ref()
+                .is_some_and(|nft_mint| nft_mint.mint_authority.is_some()),
+            CustomError::CampaignNftCannotJoinGroup
+        );
+    }
+
+    let campaign_key = ctx.accounts.campaign.key();
+    let group_seeds: &[&[u8]] = &[
+        b"campaign_group",
+        campaign_key.as_ref(),
+        &[ctx.bumps.group_mint],
+    ];
+    let authority_seeds: &[&[u8]] = &[
+        b"mint_authority",
+        campaign_key.as_ref(),
+        &[ctx.bumps.mint_authority],
+    ];
+    let group_mint_key = ctx.accounts.group_mint.key();
+    let mint_authority_key = ctx.accounts.mint_authority.key();
+
+    // The group TLV is appended by token_group_initialize, so pre-fund its rent
+    let mint_len =
+        ExtensionType::try_calculate_account_len::<MintState>(&[ExtensionType::GroupPointer])?;
+    let funded_len = ExtensionType::try_calculate_account_len::<MintState>(&[
+        ExtensionType::GroupPointer,
+        ExtensionType::TokenGroup,
+    ])?;
+    let lamports = Rent::get()?.minimum_balance(funded_len);
+
+    create_pda_account(
+        &ctx.accounts.company.to_account_info(),
+        &ctx.accounts.group_mint.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        lamports,
+        mint_len,
+        &token_2022::ID,
+        group_seeds,
+    )?;
+
+    group_pointer_initialize(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            GroupPointerInitialize {
+                token_program_id: ctx.accounts.token_program.to_account_info(),
+                mint: ctx.accounts.group_mint.to_account_info(),
+            },
+        ),
+        Some(mint_authority_key),
+        Some(group_mint_key),
+    )?;
+
+    initialize_mint2(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            InitializeMint2 {
+                mint: ctx.accounts.group_mint.to_account_info(),
+            },
+        ),
+        0,
+        &mint_authority_key,
+        None,
+    )?;
+
+    token_group_initialize(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TokenGroupInitialize {
+                token_program_id: ctx.accounts.token_program.to_account_info(),
+                group: ctx.accounts.group_mint.to_account_info(),
+                mint: ctx.accounts.group_mint.to_account_info(),
+                mint_authority: ctx.accounts.mint_authority.to_account_info(),
+            },
+            &[authority_seeds],
+        ),
+        Some(mint_authority_key),
+        max_size,
+    )?;
+
+    let campaign = &mut ctx.accounts.campaign;
+    campaign.group_mint = group_mint_key;
+
+    emit!(CampaignGroupCreatedEvent {
+        campaign: campaign_key,
+        group_mint: group_mint_key,
+        max_size,
+    });
+
+    Ok(())
+}
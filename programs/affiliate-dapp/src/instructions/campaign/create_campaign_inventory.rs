@@ -0,0 +1,111 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{TokenAccount, TokenInterface},
+};
+
+use crate::{error::CustomError, group::*, metadata::*, state::*};
+
+#[derive(Accounts)]
+pub struct CreateCampaignInventory<'info> {
+    #[account(mut)]
+    pub company: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        has_one = company @ CustomError::Unauthorized,
+        constraint = campaign.mode == CampaignMode::Escrow @ CustomError::InvalidCampaignMode,
+        constraint = campaign.selection_mode == SelectionMode::BuyerChoice @ CustomError::InvalidSelectionMode,
+        // Draws hold the company share until delivery, which only works for lamports
+        constraint = campaign.payment_mint == Pubkey::default() @ CustomError::RandomDrawCurrency,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    /// CHECK: The PDA for the NFT escrow account
+    #[account(
+        seeds = [b"nft_escrow", campaign.key().as_ref()],
+        bump,
+    )]
+    pub nft_escrow: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = company,
+        space = 8 + CampaignInventory::INIT_SPACE,
+        seeds = [b"inventory", campaign.key().as_ref()],
+        bump,
+    )]
+    pub inventory: Account<'info, CampaignInventory>,
+
+    #[account(address = campaign.token_program @ CustomError::InvalidTokenProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when a campaign switches to random selection
+#[event]
+pub struct InventoryCreatedEvent {
+    pub campaign: Pubkey,
+    pub entries: u64,
+}
+
+// Each NFT the escrow holds is passed in remaining_accounts as
+// [nft_mint, campaign escrow token account, metadata PDA]
+const INVENTORY_NFT_ACCOUNTS: usize = 3;
+
+// Switches the campaign to random selection. Every escrowed NFT is listed once per token,
+// so the list has to cover the whole available supply. Draws can't be steered away from
+// an NFT, so each one must be a member of the campaign's group and collection, and its
+// royalties must fit in the company share; the group can't change afterwards.
+pub fn create_campaign_inventory_instruction<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CreateCampaignInventory<'info>>,
+) -> Result<()> {
+    let nft_escrow = ctx.accounts.nft_escrow.key();
+    let token_program = ctx.accounts.token_program.key();
+    let campaign = &ctx.accounts.campaign;
+    let inventory = &mut ctx.accounts.inventory;
+    inventory.campaign = campaign.key();
+    inventory.bump = ctx.bumps.inventory;
+
+    let mut listed_mints: Vec<Pubkey> = Vec::with_capacity(ctx.remaining_accounts.len());
+    for nft_accounts in ctx.remaining_accounts.chunks(INVENTORY_NFT_ACCOUNTS) {
+        let [nft_mint, escrow_token_account, nft_metadata] = nft_accounts else {
+            return err!(CustomError::InvalidRemainingAccounts);
+        };
+        require_keys_eq!(*nft_mint.owner, token_program, CustomError::InvalidTokenProgram);
+        let mint = nft_mint.key();
+        // Only the escrow ATAs, and each of them once, so no token is listed twice
+        require_keys_eq!(
+            escrow_token_account.key(),
+            get_associated_token_address_with_program_id(&nft_escrow, &mint, &token_program),
+            CustomError::InvalidRemainingAccounts
+        );
+        require!(!listed_mints.contains(&mint), CustomError::InvalidRemainingAccounts);
+        require_group_member(campaign, nft_mint)?;
+        require_collection_member(campaign, nft_mint, Some(nft_metadata))?;
+        let royalty = load_royalty(&mint, nft_metadata)?;
+        inventory.add_royalty(
+            royalty.map_or(0, |royalty| royalty.seller_fee_basis_points),
+            campaign.commission_bps,
+        )?;
+        let escrow_token_account = InterfaceAccount::<TokenAccount>::try_from(escrow_token_account)?;
+        listed_mints.push(mint);
+        inventory.add(mint, escrow_token_account.amount)?;
+    }
+
+    let campaign = &mut ctx.accounts.campaign;
+    require!(
+        inventory.mints.len() as u64 == campaign.available_supply,
+        CustomError::InventoryOutOfSync
+    );
+    campaign.selection_mode = SelectionMode::Random;
+
+    emit!(InventoryCreatedEvent {
+        campaign: campaign.key(),
+        entries: campaign.available_supply,
+    });
+
+    Ok(())
+}
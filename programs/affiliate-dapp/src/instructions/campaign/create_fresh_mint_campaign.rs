@@ -0,0 +1,91 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{token_2022, token_interface::Mint};
+
+use crate::{error::CustomError, instructions::ListingCreatedEvent, state::*};
+
+#[derive(Accounts)]
+#[instruction(name: String, mint_price: u64, commission_bps: u16, campaign_details: String, base_uri: String)]
+pub struct CreateFreshMintCampaign<'info> {
+    #[account(mut)]
+    pub company: Signer<'info>,
+
+    #[account(
+        init,
+        payer = company,
+        space = NFTCampaign::space(name.len(), campaign_details.len(), base_uri.len()),
+        seeds = [b"nft_campaign", company.key().as_ref(), name.as_bytes()],
+        bump,
+        constraint = name.len() <= 32,
+        constraint = campaign_details.len() <= 200,
+    )]
+    pub campaign: Box<Account<'info, NFTCampaign>>,
+
+    #[account(
+        seeds = [b"marketplace_authority"],
+        bump = marketplace_authority.bump,
+        constraint = !marketplace_authority.paused @ CustomError::ProtocolPaused,
+    )]
+    pub marketplace_authority: Account<'info, ProtocolConfig>,
+
+    // Token buyers pay with; lamports when omitted
+    pub payment_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Campaigns in this mode hold no inventory: each affiliate purchase mints a new
+// Token-2022 NFT named "<campaign name> #<sequence>" with URI "<base_uri><sequence>",
+// whose mint and metadata authority is the campaign's [b"mint_authority", campaign] PDA.
+pub fn create_fresh_mint_campaign_instruction(
+    ctx: Context<CreateFreshMintCampaign>,
+    name: String,
+    mint_price: u64,
+    commission_bps: u16,
+    campaign_details: String,
+    base_uri: String,
+    max_supply: u64,
+) -> Result<()> {
+    require!(mint_price > 0, CustomError::InvalidPrice);
+    require!(commission_bps <= MAX_BPS, CustomError::InvalidCommissionBps);
+    require!(!base_uri.is_empty() && base_uri.len() <= 200, CustomError::InvalidBaseUri);
+    require!(max_supply > 0, CustomError::InvalidAmount);
+
+    let campaign = &mut ctx.accounts.campaign;
+    campaign.nft_mint = Pubkey::default();
+    campaign.company = ctx.accounts.company.key();
+    campaign.name = name;
+    campaign.mint_price = mint_price;
+    campaign.commission_bps = commission_bps;
+    campaign.campaign_details = campaign_details;
+    campaign.active = true;
+    campaign.affiliates_count = 0;
+    campaign.total_mints = 0;
+    campaign.created_at = Clock::get()?.unix_timestamp;
+    // Remaining NFTs that may still be minted
+    campaign.available_supply = max_supply;
+    campaign.token_program = token_2022::ID;
+    campaign.mode = CampaignMode::FreshMint;
+    campaign.base_uri = base_uri;
+    campaign.fresh_mint_count = 0;
+    campaign.group_mint = Pubkey::default();
+    campaign.payment_mint = ctx.accounts.payment_mint.as_ref().map_or(Pubkey::default(), |mint| mint.key());
+    campaign.price_feed = Pubkey::default();
+    campaign.max_price_age = 0;
+    campaign.direct_sales = 0;
+    campaign.collection_mint = Pubkey::default();
+    campaign.selection_mode = SelectionMode::BuyerChoice;
+    campaign.pending_draws = 0;
+    campaign.auction = None;
+    campaign.legacy_campaign = Pubkey::default();
+
+    emit!(ListingCreatedEvent {
+        listing: campaign.key(),
+        seller: campaign.company,
+        nft_mint: campaign.nft_mint,
+        price: mint_price,
+        payment_mint: campaign.payment_mint,
+        created_at: campaign.created_at,
+    });
+
+    Ok(())
+}
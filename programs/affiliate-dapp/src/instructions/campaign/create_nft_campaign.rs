@@ -1,12 +1,16 @@
 use anchor_lang::{prelude::*, solana_program::program_option::COption};
 use anchor_spl::{
-    associated_token::AssociatedToken, token_2022::ID, token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked}
+    associated_token::AssociatedToken,
+    token_interface::{
+        set_authority, spl_token_2022::instruction::AuthorityType, transfer_checked, Mint,
+        SetAuthority, TokenAccount, TokenInterface, TransferChecked,
+    },
 };
 
-use crate::state::*;
+use crate::{error::CustomError, metadata::require_collection_member, state::*};
 
 #[derive(Accounts)]
-#[instruction(name: String, mint_price: u64, commission_percentage: u8, campaign_details: String)]
+#[instruction(name: String, mint_price: u64, commission_bps: u16, campaign_details: String)]
 pub struct CreateNFTCampaign<'info> {
 
     #[account(mut)]
@@ -14,8 +18,8 @@ pub struct CreateNFTCampaign<'info> {
     #[account(
         init,
         payer = company,
-        space = 8 + 32 + 32 + 32 + (4 + name.len()) + 8 + 1 + (4 + campaign_details.len()) + 1 + 8 + 8,
-        seeds = [b"nft_campaign", name.as_bytes()],
+        space = NFTCampaign::space(name.len(), campaign_details.len(), 0),
+        seeds = [b"nft_campaign", company.key().as_ref(), name.as_bytes()],
         bump,
         constraint = name.len() <= 32,
         constraint = campaign_details.len() <= 200,
@@ -24,9 +28,17 @@ pub struct CreateNFTCampaign<'info> {
     
     #[account(
         mut,
-        constraint = nft_mint.mint_authority == COption::Some(company.key())
+        constraint = nft_mint.mint_authority == COption::Some(company.key()),
+        constraint = nft_mint.decimals == 0 @ CustomError::InvalidNftDecimals,
     )]
     pub nft_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Takes over the NFT's mint authority with MintAuthorityLock::Campaign
+    #[account(
+        seeds = [b"mint_authority", campaign.key().as_ref()],
+        bump,
+    )]
+    pub mint_authority: UncheckedAccount<'info>,
     
     
 
@@ -52,9 +64,24 @@ pub struct CreateNFTCampaign<'info> {
         payer = company,
         associated_token::mint = nft_mint,
         associated_token::authority = nft_escrow, // Authority is the PDA!
+        associated_token::token_program = token_program,
     )]
     pub escrow_pda_nft_token_account: InterfaceAccount<'info, TokenAccount>,
-    #[account(address = ID)]
+
+    #[account(
+        seeds = [b"marketplace_authority"],
+        bump = marketplace_authority.bump,
+        constraint = !marketplace_authority.paused @ CustomError::ProtocolPaused,
+    )]
+    pub marketplace_authority: Account<'info, ProtocolConfig>,
+    // Token buyers pay with; lamports when omitted
+    pub payment_mint: Option<InterfaceAccount<'info, Mint>>,
+    // Collection every escrowed NFT must be a verified member of; any NFT when omitted
+    pub collection_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    /// CHECK: Metaplex metadata PDA of nft_mint, checked in require_collection_member
+    pub nft_metadata: Option<UncheckedAccount<'info>>,
+
+    // Either SPL Token or Token-2022; later instructions must use the same one
     pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
@@ -66,6 +93,7 @@ pub struct ListingCreatedEvent {
     pub seller: Pubkey,
     pub nft_mint: Pubkey,
     pub price: u64,
+    pub payment_mint: Pubkey,
     pub created_at: i64,
 }
 
@@ -93,26 +121,53 @@ pub fn create_nft_campaign_instruction(
     ctx: Context<CreateNFTCampaign>,
     name: String,
     mint_price: u64,
-    commission_percentage: u8,
+    commission_bps: u16,
     campaign_details: String,
+    mint_authority_lock: MintAuthorityLock,
 ) -> Result<()> {
+    require!(mint_price > 0, CustomError::InvalidPrice);
+    require!(commission_bps <= MAX_BPS, CustomError::InvalidCommissionBps);
+    // A one-of-one only: every token of the mint ends up in the escrow
+    require!(ctx.accounts.nft_mint.supply == 1, CustomError::NftSupplyMismatch);
+
     let campaign = &mut ctx.accounts.campaign;
     campaign.nft_mint =ctx.accounts.nft_mint.key(); 
+    campaign.token_program = ctx.accounts.token_program.key();
     campaign.company = ctx.accounts.company.key();
     campaign.name = name;
     campaign.mint_price = mint_price;
-    campaign.commission_percentage = commission_percentage;
+    campaign.commission_bps = commission_bps;
     campaign.campaign_details = campaign_details;
     campaign.active = true; 
     campaign.affiliates_count = 0;
     campaign.total_mints = 0;
     campaign.created_at = Clock::get()?.unix_timestamp;
+    campaign.available_supply = 1;
+    campaign.mode = CampaignMode::Escrow;
+    campaign.base_uri = String::new();
+    campaign.fresh_mint_count = 0;
+    campaign.group_mint = Pubkey::default();
+    campaign.payment_mint = ctx.accounts.payment_mint.as_ref().map_or(Pubkey::default(), |mint| mint.key());
+    campaign.price_feed = Pubkey::default();
+    campaign.max_price_age = 0;
+    campaign.direct_sales = 0;
+    campaign.collection_mint = ctx.accounts.collection_mint.as_ref().map_or(Pubkey::default(), |mint| mint.key());
+    campaign.selection_mode = SelectionMode::BuyerChoice;
+    campaign.pending_draws = 0;
+    campaign.auction = None;
+    campaign.legacy_campaign = Pubkey::default();
+    require_collection_member(
+        campaign,
+        &ctx.accounts.nft_mint.to_account_info(),
+        ctx.accounts.nft_metadata.as_ref().map(|metadata| metadata.as_ref()),
+    )?;
 
     emit!(ListingCreatedEvent {
         listing: campaign.key(),
         seller: campaign.company.key(),
         nft_mint:campaign.nft_mint.key(),
         price: mint_price,
+        payment_mint: campaign.payment_mint,
         created_at: campaign.created_at,
     });
     //transfer the NFT to the escrow account
@@ -127,6 +182,24 @@ pub fn create_nft_campaign_instruction(
     // let cpi_ctx = CpiContext::new(cpi_program, transfer_instruction );
     let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), transfer_accounts);
     transfer_checked(cpi_ctx,1,ctx.accounts.nft_mint.decimals)?;
+
+    // Optionally stop the company from printing duplicates of the escrowed NFT
+    let new_authority = match mint_authority_lock {
+        MintAuthorityLock::Keep => return Ok(()),
+        MintAuthorityLock::Revoke => None,
+        MintAuthorityLock::Campaign => Some(ctx.accounts.mint_authority.key()),
+    };
+    set_authority(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            SetAuthority {
+                current_authority: ctx.accounts.company.to_account_info(),
+                account_or_mint: ctx.accounts.nft_mint.to_account_info(),
+            },
+        ),
+        AuthorityType::MintTokens,
+        new_authority,
+    )?;
    
     Ok(())
 }
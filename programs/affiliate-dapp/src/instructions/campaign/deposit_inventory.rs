@@ -0,0 +1,124 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{error::CustomError, group::*, metadata::*, state::*};
+
+#[derive(Accounts)]
+pub struct DepositInventory<'info> {
+    #[account(mut)]
+    pub company: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        has_one = company @ CustomError::Unauthorized,
+        constraint = campaign.mode == CampaignMode::Escrow @ CustomError::InvalidCampaignMode,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    // Any mint can be added, not only the one the campaign was created with
+    #[account(constraint = nft_mint.decimals == 0 @ CustomError::InvalidNftDecimals)]
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: Metaplex metadata PDA of nft_mint, checked in require_collection_member and load_royalty;
+    /// required once the campaign draws NFTs at random
+    pub nft_metadata: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = company,
+        associated_token::token_program = token_program,
+    )]
+    pub project_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: The PDA for the NFT escrow account
+    #[account(
+        seeds = [b"nft_escrow", campaign.key().as_ref()],
+        bump,
+    )]
+    pub nft_escrow: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = company,
+        associated_token::mint = nft_mint,
+        associated_token::authority = nft_escrow, // Authority is the PDA!
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_pda_nft_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    // Required once the campaign draws NFTs at random
+    #[account(
+        mut,
+        seeds = [b"inventory", campaign.key().as_ref()],
+        bump = inventory.bump,
+    )]
+    pub inventory: Option<Box<Account<'info, CampaignInventory>>>,
+
+    #[account(address = campaign.token_program @ CustomError::InvalidTokenProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when the company adds NFTs to the campaign escrow
+#[event]
+pub struct InventoryDepositedEvent {
+    pub campaign: Pubkey,
+    pub nft_mint: Pubkey,
+    pub amount: u64,
+    pub available_supply: u64,
+}
+
+pub fn deposit_inventory_instruction(ctx: Context<DepositInventory>, amount: u64) -> Result<()> {
+    // A one-of-one only, as at campaign creation: its single token is what gets escrowed
+    require!(ctx.accounts.nft_mint.supply == 1, CustomError::NftSupplyMismatch);
+    require!(amount == 1, CustomError::InvalidAmount);
+    require_group_member(&ctx.accounts.campaign, &ctx.accounts.nft_mint.to_account_info())?;
+    require_collection_member(
+        &ctx.accounts.campaign,
+        &ctx.accounts.nft_mint.to_account_info(),
+        ctx.accounts.nft_metadata.as_ref().map(|metadata| metadata.as_ref()),
+    )?;
+
+    let transfer_accounts = TransferChecked {
+        from: ctx.accounts.project_token_account.to_account_info(),
+        mint: ctx.accounts.nft_mint.to_account_info(),
+        to: ctx.accounts.escrow_pda_nft_token_account.to_account_info(),
+        authority: ctx.accounts.company.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), transfer_accounts);
+    transfer_checked(cpi_ctx, amount, ctx.accounts.nft_mint.decimals)?;
+
+    // Pending draws pick from the inventory as it stands, which the company mustn't reshape
+    if ctx.accounts.campaign.selection_mode == SelectionMode::Random {
+        require!(ctx.accounts.campaign.pending_draws == 0, CustomError::PendingRandomDraws);
+        let nft_metadata = ctx.accounts.nft_metadata.as_ref().ok_or(CustomError::MissingNftMetadata)?;
+        let royalty = load_royalty(&ctx.accounts.nft_mint.key(), nft_metadata)?;
+        let inventory = ctx.accounts.inventory.as_mut().ok_or(CustomError::MissingInventory)?;
+        inventory.add_royalty(
+            royalty.map_or(0, |royalty| royalty.seller_fee_basis_points),
+            ctx.accounts.campaign.commission_bps,
+        )?;
+        inventory.add(ctx.accounts.nft_mint.key(), amount)?;
+    }
+
+    let campaign = &mut ctx.accounts.campaign;
+    campaign.available_supply = campaign
+        .available_supply
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    emit!(InventoryDepositedEvent {
+        campaign: campaign.key(),
+        nft_mint: ctx.accounts.nft_mint.key(),
+        amount,
+        available_supply: campaign.available_supply,
+    });
+
+    Ok(())
+}
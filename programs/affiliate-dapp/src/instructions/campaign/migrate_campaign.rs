@@ -0,0 +1,173 @@
+use anchor_lang::{prelude::*, Discriminator};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_2022,
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{error::CustomError, state::*};
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct MigrateCampaign<'info> {
+    #[account(mut)]
+    pub company: Signer<'info>,
+
+    /// CHECK: Deserialized and validated as a LegacyNFTCampaign in the handler
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", name.as_bytes()],
+        bump,
+    )]
+    pub legacy_campaign: UncheckedAccount<'info>,
+
+    // The legacy details are only read in the handler, so leave room for the longest
+    #[account(
+        init,
+        payer = company,
+        space = NFTCampaign::space(name.len(), 200, 0),
+        seeds = [b"nft_campaign", company.key().as_ref(), name.as_bytes()],
+        bump,
+    )]
+    pub campaign: Box<Account<'info, NFTCampaign>>,
+
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: The escrow PDA of the legacy campaign
+    #[account(
+        seeds = [b"nft_escrow", legacy_campaign.key().as_ref()],
+        bump,
+    )]
+    pub legacy_nft_escrow: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = legacy_nft_escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub legacy_escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: The PDA for the NFT escrow account
+    #[account(
+        seeds = [b"nft_escrow", campaign.key().as_ref()],
+        bump,
+    )]
+    pub nft_escrow: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = company,
+        associated_token::mint = nft_mint,
+        associated_token::authority = nft_escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_pda_nft_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = token_2022::ID)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when a name-keyed campaign moves to its company-scoped address
+#[event]
+pub struct CampaignMigratedEvent {
+    pub legacy_campaign: Pubkey,
+    pub campaign: Pubkey,
+    pub company: Pubkey,
+    pub migrated_amount: u64,
+}
+
+pub fn migrate_campaign_instruction(ctx: Context<MigrateCampaign>, name: String) -> Result<()> {
+    let legacy: LegacyNFTCampaign = load_legacy_account(
+        &ctx.accounts.legacy_campaign.to_account_info(),
+        NFTCampaign::DISCRIMINATOR,
+    )?;
+    require_keys_eq!(legacy.company, ctx.accounts.company.key(), CustomError::Unauthorized);
+    require_keys_eq!(legacy.nft_mint, ctx.accounts.nft_mint.key(), CustomError::InvalidLegacyAccount);
+    require!(legacy.name == name, CustomError::InvalidLegacyAccount);
+
+    let campaign = &mut ctx.accounts.campaign;
+    campaign.nft_mint = legacy.nft_mint;
+    campaign.company = legacy.company;
+    campaign.name = legacy.name;
+    campaign.mint_price = legacy.mint_price;
+    campaign.commission_bps = u16::from(legacy.commission_percentage) * 100;
+    campaign.campaign_details = legacy.campaign_details;
+    campaign.active = legacy.active;
+    campaign.affiliates_count = legacy.affiliates_count;
+    campaign.total_mints = legacy.total_mints;
+    campaign.created_at = legacy.created_at;
+    // Name-keyed campaigns could only be created with Token-2022
+    campaign.token_program = ctx.accounts.token_program.key();
+    campaign.mode = CampaignMode::Escrow;
+    campaign.base_uri = String::new();
+    campaign.fresh_mint_count = 0;
+    campaign.group_mint = Pubkey::default();
+    campaign.payment_mint = Pubkey::default();
+    campaign.price_feed = Pubkey::default();
+    campaign.max_price_age = 0;
+    campaign.direct_sales = 0;
+    campaign.collection_mint = Pubkey::default();
+    campaign.selection_mode = SelectionMode::BuyerChoice;
+    campaign.pending_draws = 0;
+    campaign.auction = None;
+    // Ties the legacy affiliate links to this company's campaign in migrate_affiliate_link
+    campaign.legacy_campaign = ctx.accounts.legacy_campaign.key();
+    campaign.available_supply = ctx.accounts.legacy_escrow_token_account.amount;
+
+    // The escrow PDA is derived from the campaign address, so move the NFT along with it
+    let legacy_campaign_key = campaign.legacy_campaign;
+    let legacy_escrow_seeds = [
+        b"nft_escrow",
+        legacy_campaign_key.as_ref(),
+        &[ctx.bumps.legacy_nft_escrow],
+    ];
+    let legacy_escrow_seeds_ref = [&legacy_escrow_seeds[..]];
+
+    let migrated_amount = ctx.accounts.legacy_escrow_token_account.amount;
+    if migrated_amount > 0 {
+        let transfer_accounts = TransferChecked {
+            from: ctx.accounts.legacy_escrow_token_account.to_account_info(),
+            mint: ctx.accounts.nft_mint.to_account_info(),
+            to: ctx.accounts.escrow_pda_nft_token_account.to_account_info(),
+            authority: ctx.accounts.legacy_nft_escrow.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_accounts,
+            &legacy_escrow_seeds_ref,
+        );
+        transfer_checked(cpi_ctx, migrated_amount, ctx.accounts.nft_mint.decimals)?;
+    }
+
+    let close_accounts = CloseAccount {
+        account: ctx.accounts.legacy_escrow_token_account.to_account_info(),
+        destination: ctx.accounts.company.to_account_info(),
+        authority: ctx.accounts.legacy_nft_escrow.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        close_accounts,
+        &legacy_escrow_seeds_ref,
+    );
+    close_account(cpi_ctx)?;
+
+    close_legacy_account(
+        &ctx.accounts.legacy_campaign.to_account_info(),
+        &ctx.accounts.company.to_account_info(),
+    )?;
+
+    emit!(CampaignMigratedEvent {
+        legacy_campaign: legacy_campaign_key,
+        campaign: campaign.key(),
+        company: campaign.company,
+        migrated_amount,
+    });
+
+    Ok(())
+}
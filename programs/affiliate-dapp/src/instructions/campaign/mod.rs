@@ -1,2 +1,30 @@
 pub use create_nft_campaign::*;
-pub mod create_nft_campaign;
\ No newline at end of file
+pub mod create_nft_campaign;
+pub use pause_campaign::*;
+pub mod pause_campaign;
+pub use resume_campaign::*;
+pub mod resume_campaign;
+pub use close_campaign::*;
+pub mod close_campaign;
+pub use update_campaign::*;
+pub mod update_campaign;
+pub use migrate_campaign::*;
+pub mod migrate_campaign;
+pub use deposit_inventory::*;
+pub mod deposit_inventory;
+pub use withdraw_inventory::*;
+pub mod withdraw_inventory;
+pub use create_fresh_mint_campaign::*;
+pub mod create_fresh_mint_campaign;
+pub use create_campaign_group::*;
+pub mod create_campaign_group;
+pub use add_group_member::*;
+pub mod add_group_member;
+pub use set_price_table::*;
+pub mod set_price_table;
+pub use set_price_feed::*;
+pub mod set_price_feed;
+pub use create_campaign_inventory::*;
+pub mod create_campaign_inventory;
+pub use set_dutch_auction::*;
+pub mod set_dutch_auction;
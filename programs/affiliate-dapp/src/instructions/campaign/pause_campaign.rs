@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::CustomError, state::*};
+
+#[derive(Accounts)]
+pub struct PauseCampaign<'info> {
+    pub company: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        has_one = company @ CustomError::Unauthorized,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+}
+
+// Event emitted when a campaign stops accepting affiliate mints
+#[event]
+pub struct CampaignPausedEvent {
+    pub campaign: Pubkey,
+    pub company: Pubkey,
+    pub paused_at: i64,
+}
+
+pub fn pause_campaign_instruction(ctx: Context<PauseCampaign>) -> Result<()> {
+    let campaign = &mut ctx.accounts.campaign;
+    require!(campaign.active, CustomError::CampaignAlreadyPaused);
+
+    campaign.active = false;
+
+    emit!(CampaignPausedEvent {
+        campaign: campaign.key(),
+        company: campaign.company,
+        paused_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::CustomError, state::*};
+
+#[derive(Accounts)]
+pub struct ResumeCampaign<'info> {
+    pub company: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        has_one = company @ CustomError::Unauthorized,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+}
+
+// Event emitted when a paused campaign accepts affiliate mints again
+#[event]
+pub struct CampaignResumedEvent {
+    pub campaign: Pubkey,
+    pub company: Pubkey,
+    pub resumed_at: i64,
+}
+
+pub fn resume_campaign_instruction(ctx: Context<ResumeCampaign>) -> Result<()> {
+    let campaign = &mut ctx.accounts.campaign;
+    require!(!campaign.active, CustomError::CampaignAlreadyActive);
+
+    campaign.active = true;
+
+    emit!(CampaignResumedEvent {
+        campaign: campaign.key(),
+        company: campaign.company,
+        resumed_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::CustomError, state::*};
+
+#[derive(Accounts)]
+pub struct SetDutchAuction<'info> {
+    pub company: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        has_one = company @ CustomError::Unauthorized,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+}
+
+// Event emitted when a campaign starts or stops pricing through a Dutch auction
+#[event]
+pub struct DutchAuctionUpdatedEvent {
+    pub campaign: Pubkey,
+    pub auction: Option<DutchAuction>,
+}
+
+// While an auction is set it replaces mint_price in every sale, in the same unit
+// (lamports, payment_mint base units, or USD cents with a price feed); None goes
+// back to the fixed mint_price.
+pub fn set_dutch_auction_instruction(
+    ctx: Context<SetDutchAuction>,
+    auction: Option<DutchAuction>,
+) -> Result<()> {
+    if let Some(auction) = &auction {
+        auction.validate()?;
+    }
+
+    let campaign = &mut ctx.accounts.campaign;
+    campaign.auction = auction;
+
+    emit!(DutchAuctionUpdatedEvent {
+        campaign: campaign.key(),
+        auction,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::CustomError, oracle::PriceFeed, state::*};
+
+#[derive(Accounts)]
+pub struct SetPriceFeed<'info> {
+    pub company: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        has_one = company @ CustomError::Unauthorized,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    /// CHECK: Parsed as a SOL/USD price feed; omitted to go back to lamport pricing
+    pub price_feed: Option<UncheckedAccount<'info>>,
+}
+
+// Event emitted when a campaign switches between USD and lamport pricing
+#[event]
+pub struct PriceFeedUpdatedEvent {
+    pub campaign: Pubkey,
+    pub price_feed: Pubkey,
+    pub mint_price: u64,
+    pub max_price_age: u64,
+}
+
+// With a feed, `mint_price` is in USD cents and converted to lamports at every sale;
+// without one it is a plain lamport price again. The feed address is pinned so
+// purchases can't substitute another account.
+pub fn set_price_feed_instruction(
+    ctx: Context<SetPriceFeed>,
+    mint_price: u64,
+    max_price_age: u64,
+) -> Result<()> {
+    require!(mint_price > 0, CustomError::InvalidPrice);
+
+    let campaign = &mut ctx.accounts.campaign;
+    match &ctx.accounts.price_feed {
+        Some(price_feed) => {
+            require!(
+                campaign.payment_mint == Pubkey::default(),
+                CustomError::PriceFeedRequiresLamports
+            );
+            PriceFeed::load(price_feed)?;
+            campaign.price_feed = price_feed.key();
+            campaign.max_price_age = max_price_age;
+        }
+        None => {
+            campaign.price_feed = Pubkey::default();
+            campaign.max_price_age = 0;
+        }
+    }
+    campaign.mint_price = mint_price;
+
+    emit!(PriceFeedUpdatedEvent {
+        campaign: campaign.key(),
+        price_feed: campaign.price_feed,
+        mint_price,
+        max_price_age: campaign.max_price_age,
+    });
+
+    Ok(())
+}
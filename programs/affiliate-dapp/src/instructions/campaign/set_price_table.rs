@@ -0,0 +1,62 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::CustomError, state::*};
+
+#[derive(Accounts)]
+pub struct SetPriceTable<'info> {
+    #[account(mut)]
+    pub company: Signer<'info>,
+
+    #[account(
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        has_one = company @ CustomError::Unauthorized,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    #[account(
+        init_if_needed,
+        payer = company,
+        space = 8 + CampaignPriceTable::INIT_SPACE,
+        seeds = [b"price_table", campaign.key().as_ref()],
+        bump,
+    )]
+    pub price_table: Account<'info, CampaignPriceTable>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when a campaign publishes its accepted currencies
+#[event]
+pub struct PriceTableUpdatedEvent {
+    pub campaign: Pubkey,
+    pub prices: Vec<CurrencyPrice>,
+}
+
+// Replaces the whole table. The campaign's own mint_price and payment_mint stay the
+// default currency, so they can't be listed again here; an empty table removes the extras.
+pub fn set_price_table_instruction(ctx: Context<SetPriceTable>, prices: Vec<CurrencyPrice>) -> Result<()> {
+    require!(prices.len() <= MAX_PRICE_TABLE_ENTRIES, CustomError::PriceTableTooLarge);
+
+    let campaign = &ctx.accounts.campaign;
+    for (index, entry) in prices.iter().enumerate() {
+        require!(entry.price > 0, CustomError::InvalidPrice);
+        require!(
+            entry.mint != campaign.payment_mint
+                && !prices[..index].iter().any(|other| other.mint == entry.mint),
+            CustomError::DuplicateCurrency
+        );
+    }
+
+    let price_table = &mut ctx.accounts.price_table;
+    price_table.campaign = campaign.key();
+    price_table.prices = prices;
+    price_table.bump = ctx.bumps.price_table;
+
+    emit!(PriceTableUpdatedEvent {
+        campaign: price_table.campaign,
+        prices: price_table.prices.clone(),
+    });
+
+    Ok(())
+}
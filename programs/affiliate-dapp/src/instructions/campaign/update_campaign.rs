@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::CustomError, state::*};
+
+#[derive(Accounts)]
+#[instruction(mint_price: Option<u64>, commission_bps: Option<u16>, campaign_details: Option<String>)]
+pub struct UpdateCampaign<'info> {
+    #[account(mut)]
+    pub company: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        has_one = company @ CustomError::Unauthorized,
+        // campaign_details is sized to its length, so resize to fit the new value
+        realloc = NFTCampaign::space(
+            campaign.name.len(),
+            campaign_details.as_ref().map_or(campaign.campaign_details.len(), |details| details.len()),
+            campaign.base_uri.len(),
+        ),
+        realloc::payer = company,
+        realloc::zero = false,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    // Required to change the commission once the campaign draws NFTs at random
+    #[account(
+        seeds = [b"inventory", campaign.key().as_ref()],
+        bump = inventory.bump,
+    )]
+    pub inventory: Option<Box<Account<'info, CampaignInventory>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when the campaign mint price changes
+#[event]
+pub struct CampaignPriceUpdatedEvent {
+    pub campaign: Pubkey,
+    pub old_mint_price: u64,
+    pub new_mint_price: u64,
+    pub updated_at: i64,
+}
+
+// Event emitted when the affiliate commission changes
+#[event]
+pub struct CampaignCommissionUpdatedEvent {
+    pub campaign: Pubkey,
+    pub old_commission_bps: u16,
+    pub new_commission_bps: u16,
+    pub updated_at: i64,
+}
+
+// Event emitted when the campaign details change
+#[event]
+pub struct CampaignDetailsUpdatedEvent {
+    pub campaign: Pubkey,
+    pub old_campaign_details: String,
+    pub new_campaign_details: String,
+    pub updated_at: i64,
+}
+
+pub fn update_campaign_instruction(
+    ctx: Context<UpdateCampaign>,
+    mint_price: Option<u64>,
+    commission_bps: Option<u16>,
+    campaign_details: Option<String>,
+) -> Result<()> {
+    let campaign = &mut ctx.accounts.campaign;
+    let campaign_key = campaign.key();
+    let updated_at = Clock::get()?.unix_timestamp;
+
+    if let Some(mint_price) = mint_price {
+        require!(mint_price > 0, CustomError::InvalidPrice);
+
+        emit!(CampaignPriceUpdatedEvent {
+            campaign: campaign_key,
+            old_mint_price: campaign.mint_price,
+            new_mint_price: mint_price,
+            updated_at,
+        });
+        campaign.mint_price = mint_price;
+    }
+
+    if let Some(commission_bps) = commission_bps {
+        require!(commission_bps <= MAX_BPS, CustomError::InvalidCommissionBps);
+        // Draws are paid from what the commission leaves, which must still cover listed royalties
+        if campaign.selection_mode == SelectionMode::Random {
+            let inventory = ctx.accounts.inventory.as_ref().ok_or(CustomError::MissingInventory)?;
+            inventory.require_royalty_covered(commission_bps)?;
+        }
+
+        emit!(CampaignCommissionUpdatedEvent {
+            campaign: campaign_key,
+            old_commission_bps: campaign.commission_bps,
+            new_commission_bps: commission_bps,
+            updated_at,
+        });
+        campaign.commission_bps = commission_bps;
+    }
+
+    if let Some(campaign_details) = campaign_details {
+        require!(campaign_details.len() <= 200, CustomError::CampaignDetailsTooLong);
+
+        let old_campaign_details = std::mem::replace(&mut campaign.campaign_details, campaign_details);
+        emit!(CampaignDetailsUpdatedEvent {
+            campaign: campaign_key,
+            old_campaign_details,
+            new_campaign_details: campaign.campaign_details.clone(),
+            updated_at,
+        });
+    }
+
+    Ok(())
+}
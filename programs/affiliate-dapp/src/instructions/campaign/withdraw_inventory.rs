@@ -0,0 +1,143 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{error::CustomError, state::*};
+
+#[derive(Accounts)]
+pub struct WithdrawInventory<'info> {
+    #[account(mut)]
+    pub company: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"nft_campaign", campaign.company.as_ref(), campaign.name.as_bytes()],
+        bump,
+        has_one = company @ CustomError::Unauthorized,
+        constraint = campaign.mode == CampaignMode::Escrow @ CustomError::InvalidCampaignMode,
+    )]
+    pub campaign: Account<'info, NFTCampaign>,
+
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: The PDA for the NFT escrow account
+    #[account(
+        seeds = [b"nft_escrow", campaign.key().as_ref()],
+        bump,
+    )]
+    pub nft_escrow: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = nft_escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub escrow_pda_nft_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = company,
+        associated_token::mint = nft_mint,
+        associated_token::authority = company,
+        associated_token::token_program = token_program,
+    )]
+    pub project_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    // Required once the campaign draws NFTs at random
+    #[account(
+        mut,
+        seeds = [b"inventory", campaign.key().as_ref()],
+        bump = inventory.bump,
+    )]
+    pub inventory: Option<Box<Account<'info, CampaignInventory>>>,
+
+    #[account(address = campaign.token_program @ CustomError::InvalidTokenProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted when the company takes NFTs back out of the campaign escrow
+#[event]
+pub struct InventoryWithdrawnEvent {
+    pub campaign: Pubkey,
+    pub nft_mint: Pubkey,
+    pub amount: u64,
+    pub available_supply: u64,
+    pub escrow_closed: bool,
+}
+
+// Withdrawing zero is only allowed to close an escrow token account that sales already emptied
+pub fn withdraw_inventory_instruction(ctx: Context<WithdrawInventory>, amount: u64) -> Result<()> {
+    let escrowed_amount = ctx.accounts.escrow_pda_nft_token_account.amount;
+    require!(amount > 0 || escrowed_amount == 0, CustomError::InvalidAmount);
+
+    // Pending draws still pick from the inventory, so the company can't take out an NFT it
+    // already knows a draw will land on
+    if ctx.accounts.campaign.selection_mode == SelectionMode::Random {
+        require!(ctx.accounts.campaign.pending_draws == 0, CustomError::PendingRandomDraws);
+        let inventory = ctx.accounts.inventory.as_mut().ok_or(CustomError::MissingInventory)?;
+        inventory.remove(&ctx.accounts.nft_mint.key(), amount)?;
+    }
+
+    let campaign_key = ctx.accounts.campaign.key();
+    let escrow_seeds = [
+        b"nft_escrow",
+        campaign_key.as_ref(),
+        &[ctx.bumps.nft_escrow],
+    ];
+    let escrow_seeds_ref = [&escrow_seeds[..]];
+
+    if amount > 0 {
+        let transfer_accounts = TransferChecked {
+            from: ctx.accounts.escrow_pda_nft_token_account.to_account_info(),
+            mint: ctx.accounts.nft_mint.to_account_info(),
+            to: ctx.accounts.project_token_account.to_account_info(),
+            authority: ctx.accounts.nft_escrow.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_accounts,
+            &escrow_seeds_ref,
+        );
+        transfer_checked(cpi_ctx, amount, ctx.accounts.nft_mint.decimals)?;
+    }
+
+    // Reclaim the rent of an escrow token account that no longer holds anything
+    let escrow_closed = escrowed_amount == amount;
+    if escrow_closed {
+        let close_accounts = CloseAccount {
+            account: ctx.accounts.escrow_pda_nft_token_account.to_account_info(),
+            destination: ctx.accounts.company.to_account_info(),
+            authority: ctx.accounts.nft_escrow.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            close_accounts,
+            &escrow_seeds_ref,
+        );
+        close_account(cpi_ctx)?;
+    }
+
+    let campaign = &mut ctx.accounts.campaign;
+    campaign.available_supply = campaign
+        .available_supply
+        .checked_sub(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    emit!(InventoryWithdrawnEvent {
+        campaign: campaign_key,
+        nft_mint: ctx.accounts.nft_mint.key(),
+        amount,
+        available_supply: campaign.available_supply,
+        escrow_closed,
+    });
+
+    Ok(())
+}
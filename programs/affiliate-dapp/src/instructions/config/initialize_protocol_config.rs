@@ -0,0 +1,62 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::CustomError, program::AffiliateDapp, state::*};
+
+#[derive(Accounts)]
+pub struct InitializeProtocolConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    // The config lives at the marketplace_authority PDA that sales already require
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProtocolConfig::INIT_SPACE,
+        seeds = [b"marketplace_authority"],
+        bump,
+    )]
+    pub marketplace_authority: Account<'info, ProtocolConfig>,
+
+    // Only the program's upgrade authority may create the config
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, AffiliateDapp>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ CustomError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Event emitted whenever the protocol config is created or changed
+#[event]
+pub struct ProtocolConfigUpdatedEvent {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub protocol_fee_bps: u16,
+    pub updated_at: i64,
+}
+
+pub fn initialize_protocol_config_instruction(
+    ctx: Context<InitializeProtocolConfig>,
+    treasury: Pubkey,
+    protocol_fee_bps: u16,
+) -> Result<()> {
+    require!(protocol_fee_bps <= MAX_BPS, CustomError::InvalidProtocolFeeBps);
+
+    let config = &mut ctx.accounts.marketplace_authority;
+    config.admin = ctx.accounts.admin.key();
+    config.treasury = treasury;
+    config.protocol_fee_bps = protocol_fee_bps;
+    config.paused = false;
+    config.bump = ctx.bumps.marketplace_authority;
+
+    emit!(ProtocolConfigUpdatedEvent {
+        admin: config.admin,
+        treasury: config.treasury,
+        protocol_fee_bps: config.protocol_fee_bps,
+        updated_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
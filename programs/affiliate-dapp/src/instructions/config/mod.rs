@@ -0,0 +1,6 @@
+pub use initialize_protocol_config::*;
+pub mod initialize_protocol_config;
+pub use update_protocol_config::*;
+pub mod update_protocol_config;
+pub use set_protocol_paused::*;
+pub mod set_protocol_paused;
@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::CustomError, state::*};
+
+#[derive(Accounts)]
+pub struct SetProtocolPaused<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"marketplace_authority"],
+        bump = marketplace_authority.bump,
+        has_one = admin @ CustomError::Unauthorized,
+    )]
+    pub marketplace_authority: Account<'info, ProtocolConfig>,
+}
+
+// Event emitted when the program-wide emergency switch is flipped
+#[event]
+pub struct ProtocolPausedEvent {
+    pub admin: Pubkey,
+    pub paused: bool,
+    pub updated_at: i64,
+}
+
+// Stops campaign creation, new links and sales; withdrawals and claims keep working
+pub fn set_protocol_paused_instruction(ctx: Context<SetProtocolPaused>, paused: bool) -> Result<()> {
+    let config = &mut ctx.accounts.marketplace_authority;
+    config.paused = paused;
+
+    emit!(ProtocolPausedEvent {
+        admin: config.admin,
+        paused,
+        updated_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
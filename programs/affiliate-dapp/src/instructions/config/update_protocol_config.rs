@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::CustomError, instructions::ProtocolConfigUpdatedEvent, state::*};
+
+#[derive(Accounts)]
+pub struct UpdateProtocolConfig<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"marketplace_authority"],
+        bump = marketplace_authority.bump,
+        has_one = admin @ CustomError::Unauthorized,
+    )]
+    pub marketplace_authority: Account<'info, ProtocolConfig>,
+}
+
+pub fn update_protocol_config_instruction(
+    ctx: Context<UpdateProtocolConfig>,
+    new_admin: Option<Pubkey>,
+    treasury: Option<Pubkey>,
+    protocol_fee_bps: Option<u16>,
+) -> Result<()> {
+    let config = &mut ctx.accounts.marketplace_authority;
+
+    if let Some(protocol_fee_bps) = protocol_fee_bps {
+        require!(protocol_fee_bps <= MAX_BPS, CustomError::InvalidProtocolFeeBps);
+        config.protocol_fee_bps = protocol_fee_bps;
+    }
+    if let Some(treasury) = treasury {
+        config.treasury = treasury;
+    }
+    if let Some(new_admin) = new_admin {
+        config.admin = new_admin;
+    }
+
+    emit!(ProtocolConfigUpdatedEvent {
+        admin: config.admin,
+        treasury: config.treasury,
+        protocol_fee_bps: config.protocol_fee_bps,
+        updated_at: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
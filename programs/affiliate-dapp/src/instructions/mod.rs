@@ -2,3 +2,5 @@ pub use campaign::*;
 pub mod campaign;
 pub use affiliate::*;
 pub mod affiliate;
+pub use config::*;
+pub mod config;
\ No newline at end of file
@@ -1,8 +1,14 @@
 use anchor_lang::prelude::*;
 mod state;
+use state::{CurrencyPrice, DutchAuction, MintAuthorityLock};
 mod instructions;
 use instructions::*;
 mod error;
+mod payment;
+mod group;
+mod oracle;
+mod metadata;
+mod random;
 //use instructions::{create_nft_campaign_instruction};
 declare_id!("Fqem6roKkvhpFtHuHDTXDzcGD9zpEgH7zVCi3Sf4hUFb");
 
@@ -14,21 +20,138 @@ pub mod affiliate_dapp {
         ctx: Context<CreateNFTCampaign>,
         name: String,
         mint_price: u64,
-        commission_percentage: u8,
+        commission_bps: u16,
         campaign_details: String,
+        mint_authority_lock: MintAuthorityLock,
     ) -> Result<()> {
-        create_nft_campaign_instruction(ctx, name, mint_price, commission_percentage, campaign_details)
+        create_nft_campaign_instruction(
+            ctx,
+            name,
+            mint_price,
+            commission_bps,
+            campaign_details,
+            mint_authority_lock,
+        )
+    }
+    pub fn create_fresh_mint_campaign(
+        ctx: Context<CreateFreshMintCampaign>,
+        name: String,
+        mint_price: u64,
+        commission_bps: u16,
+        campaign_details: String,
+        base_uri: String,
+        max_supply: u64,
+    ) -> Result<()> {
+        create_fresh_mint_campaign_instruction(ctx, name, mint_price, commission_bps, campaign_details, base_uri, max_supply)
     }
     pub fn create_affiliate_link(
         ctx: Context<CreateAffiliateLink>,
-        campaign_name: String,
     ) -> Result<()> {
-        create_affiliate_link_instruction(ctx,campaign_name)
+        create_affiliate_link_instruction(ctx)
+    }
+    pub fn process_affiliate_mint<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ProcessAffiliateMint<'info>>,
+        currency: Option<Pubkey>,
+        max_total_price: u64,
+        quantity: u64,
+    ) -> Result<()> {
+        process_affiliate_mint_instruction(ctx, currency, max_total_price, quantity)
+    }
+    pub fn process_affiliate_fresh_mint<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ProcessAffiliateFreshMint<'info>>,
+        currency: Option<Pubkey>,
+        max_total_price: u64,
+        quantity: u64,
+    ) -> Result<()> {
+        process_affiliate_fresh_mint_instruction(ctx, currency, max_total_price, quantity)
     }
-    pub fn process_affiliate_mint(
-        ctx: Context<ProcessAffiliateMint>,campaign_name: String, influencer: Pubkey
+    pub fn purchase_direct<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PurchaseDirect<'info>>,
+        currency: Option<Pubkey>,
+        max_total_price: u64,
     ) -> Result<()> {
-        process_affiliate_mint_instruction(ctx,campaign_name, influencer)
+        purchase_direct_instruction(ctx, currency, max_total_price)
+    }
+    pub fn quote_price(ctx: Context<QuotePrice>, currency: Option<Pubkey>) -> Result<u64> {
+        quote_price_instruction(ctx, currency)
+    }
+    pub fn deliver_random_draw<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DeliverRandomDraw<'info>>,
+    ) -> Result<()> {
+        deliver_random_draw_instruction(ctx)
+    }
+    pub fn renew_random_draw(ctx: Context<RenewRandomDraw>) -> Result<()> {
+        renew_random_draw_instruction(ctx)
+    }
+    pub fn claim_earnings(ctx: Context<ClaimEarnings>) -> Result<()> {
+        claim_earnings_instruction(ctx)
+    }
+    pub fn pause_campaign(ctx: Context<PauseCampaign>) -> Result<()> {
+        pause_campaign_instruction(ctx)
+    }
+    pub fn resume_campaign(ctx: Context<ResumeCampaign>) -> Result<()> {
+        resume_campaign_instruction(ctx)
+    }
+    pub fn close_campaign(ctx: Context<CloseCampaign>) -> Result<()> {
+        close_campaign_instruction(ctx)
+    }
+    pub fn update_campaign(
+        ctx: Context<UpdateCampaign>,
+        mint_price: Option<u64>,
+        commission_bps: Option<u16>,
+        campaign_details: Option<String>,
+    ) -> Result<()> {
+        update_campaign_instruction(ctx, mint_price, commission_bps, campaign_details)
+    }
+    pub fn deposit_inventory(ctx: Context<DepositInventory>, amount: u64) -> Result<()> {
+        deposit_inventory_instruction(ctx, amount)
+    }
+    pub fn withdraw_inventory(ctx: Context<WithdrawInventory>, amount: u64) -> Result<()> {
+        withdraw_inventory_instruction(ctx, amount)
+    }
+    pub fn create_campaign_inventory<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateCampaignInventory<'info>>,
+    ) -> Result<()> {
+        create_campaign_inventory_instruction(ctx)
+    }
+    pub fn create_campaign_group(ctx: Context<CreateCampaignGroup>, max_size: u32) -> Result<()> {
+        create_campaign_group_instruction(ctx, max_size)
+    }
+    pub fn add_group_member(ctx: Context<AddGroupMember>) -> Result<()> {
+        add_group_member_instruction(ctx)
+    }
+    pub fn set_price_table(ctx: Context<SetPriceTable>, prices: Vec<CurrencyPrice>) -> Result<()> {
+        set_price_table_instruction(ctx, prices)
+    }
+    pub fn set_price_feed(ctx: Context<SetPriceFeed>, mint_price: u64, max_price_age: u64) -> Result<()> {
+        set_price_feed_instruction(ctx, mint_price, max_price_age)
+    }
+    pub fn set_dutch_auction(ctx: Context<SetDutchAuction>, auction: Option<DutchAuction>) -> Result<()> {
+        set_dutch_auction_instruction(ctx, auction)
+    }
+    pub fn migrate_campaign(ctx: Context<MigrateCampaign>, name: String) -> Result<()> {
+        migrate_campaign_instruction(ctx, name)
+    }
+    pub fn migrate_affiliate_link(ctx: Context<MigrateAffiliateLink>) -> Result<()> {
+        migrate_affiliate_link_instruction(ctx)
+    }
+    pub fn initialize_protocol_config(
+        ctx: Context<InitializeProtocolConfig>,
+        treasury: Pubkey,
+        protocol_fee_bps: u16,
+    ) -> Result<()> {
+        initialize_protocol_config_instruction(ctx, treasury, protocol_fee_bps)
+    }
+    pub fn update_protocol_config(
+        ctx: Context<UpdateProtocolConfig>,
+        new_admin: Option<Pubkey>,
+        treasury: Option<Pubkey>,
+        protocol_fee_bps: Option<u16>,
+    ) -> Result<()> {
+        update_protocol_config_instruction(ctx, new_admin, treasury, protocol_fee_bps)
+    }
+    pub fn set_protocol_paused(ctx: Context<SetProtocolPaused>, paused: bool) -> Result<()> {
+        set_protocol_paused_instruction(ctx, paused)
     }
 
    
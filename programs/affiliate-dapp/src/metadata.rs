@@ -0,0 +1,132 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use mpl_token_metadata::accounts::Metadata;
+
+use crate::{
+    error::CustomError,
+    group::group_of_member,
+    payment::TokenSaleAccounts,
+    state::{bps_share, NFTCampaign},
+};
+
+// Percent shares of all creators add up to this
+const CREATOR_SHARE_TOTAL: u64 = 100;
+
+// Royalty terms of an NFT, read from its Metaplex metadata account
+#[derive(Clone, Debug)]
+pub struct Royalty {
+    pub seller_fee_basis_points: u16,
+    pub creators: Vec<(Pubkey, u8)>, // Creator address and percent share
+}
+
+impl Royalty {
+    // Each creator's cut of `sale_amount`, in metadata order
+    pub fn creator_amounts(&self, sale_amount: u64) -> Result<Vec<u64>> {
+        let royalty = bps_share(sale_amount, self.seller_fee_basis_points)?;
+        self.creators
+            .iter()
+            .map(|(_, share)| {
+                let amount = (royalty as u128) * (*share as u128) / (CREATOR_SHARE_TOTAL as u128);
+                u64::try_from(amount).map_err(|_| ProgramError::ArithmeticOverflow.into())
+            })
+            .collect()
+    }
+}
+
+// Metaplex metadata of `mint`, if it has any. `metadata` must be the mint's metadata
+// PDA: an empty system account there means there is none, anything else has to be a
+// metadata account owned by the Token Metadata program.
+pub fn load_metadata(mint: &Pubkey, metadata: &AccountInfo) -> Result<Option<Metadata>> {
+    require_keys_eq!(
+        metadata.key(),
+        Metadata::find_pda(mint).0,
+        CustomError::InvalidMetadataAccount
+    );
+    if metadata.owner == &System::id() && metadata.data_is_empty() {
+        return Ok(None);
+    }
+    require_keys_eq!(
+        *metadata.owner,
+        mpl_token_metadata::ID,
+        CustomError::InvalidMetadataAccount
+    );
+
+    let data = metadata.try_borrow_data()?;
+    let metadata =
+        Metadata::safe_deserialize(&data).map_err(|_| error!(CustomError::InvalidMetadataAccount))?;
+    require_keys_eq!(metadata.mint, *mint, CustomError::InvalidMetadataAccount);
+    Ok(Some(metadata))
+}
+
+// Royalty terms of `mint`, if it has Metaplex metadata
+pub fn load_royalty(mint: &Pubkey, metadata: &AccountInfo) -> Result<Option<Royalty>> {
+    let Some(metadata) = load_metadata(mint, metadata)? else {
+        return Ok(None);
+    };
+    Ok(Some(Royalty {
+        seller_fee_basis_points: metadata.seller_fee_basis_points,
+        creators: metadata
+            .creators
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|creator| creator.share > 0)
+            .map(|creator| (creator.address, creator.share))
+            .collect(),
+    }))
+}
+
+// Checks the accounts royalties are paid into, NFT by NFT in metadata order: the
+// creator's wallet for lamport sales, or the wallet followed by its ATA of the payment
+// mint for token sales, so the ATA can be created if the creator doesn't have one yet
+pub fn check_creator_accounts(
+    royalties: &[Option<Royalty>],
+    creator_accounts: &[AccountInfo],
+    token_sale: Option<&TokenSaleAccounts>,
+) -> Result<()> {
+    let creators: Vec<&(Pubkey, u8)> =
+        royalties.iter().flatten().flat_map(|royalty| &royalty.creators).collect();
+    let accounts_per_creator = if token_sale.is_some() { 2 } else { 1 };
+    require!(
+        creator_accounts.len() == creators.len() * accounts_per_creator,
+        CustomError::InvalidRemainingAccounts
+    );
+    for ((creator, _), accounts) in creators.into_iter().zip(creator_accounts.chunks(accounts_per_creator)) {
+        require_keys_eq!(accounts[0].key(), *creator, CustomError::InvalidCreatorAccount);
+        if let Some(token_sale) = token_sale {
+            require_keys_eq!(
+                accounts[1].key(),
+                get_associated_token_address_with_program_id(
+                    creator,
+                    token_sale.payment_mint.key,
+                    token_sale.payment_token_program.key,
+                ),
+                CustomError::InvalidCreatorAccount
+            );
+        }
+    }
+    Ok(())
+}
+
+// Campaigns without a collection accept any mint. Otherwise the mint has to be a verified
+// item of the collection in its Metaplex metadata, or a Token-2022 member of the
+// collection mint's group.
+pub fn require_collection_member(
+    campaign: &NFTCampaign,
+    mint: &AccountInfo,
+    metadata: Option<&AccountInfo>,
+) -> Result<()> {
+    if campaign.collection_mint == Pubkey::default()
+        || group_of_member(mint) == Some(campaign.collection_mint)
+    {
+        return Ok(());
+    }
+    let metadata = metadata.ok_or(CustomError::NotCollectionMember)?;
+    let collection = load_metadata(mint.key, metadata)?.and_then(|metadata| metadata.collection);
+    require!(
+        collection.is_some_and(|collection| {
+            collection.verified && collection.key == campaign.collection_mint
+        }),
+        CustomError::NotCollectionMember
+    );
+    Ok(())
+}
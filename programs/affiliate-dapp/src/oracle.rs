@@ -0,0 +1,123 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::CustomError, state::MAX_BPS};
+
+// Offsets into a Pyth v2 price account; only the fields used for pricing are read
+const MAGIC: u32 = 0xa1b2_c3d4;
+const VERSION: u32 = 2;
+const PRICE_ACCOUNT_TYPE: u32 = 3;
+const TRADING_STATUS: u32 = 1;
+const MAGIC_OFFSET: usize = 0;
+const VERSION_OFFSET: usize = 4;
+const ACCOUNT_TYPE_OFFSET: usize = 8;
+const EXPONENT_OFFSET: usize = 20;
+const PUBLISH_TIME_OFFSET: usize = 96;
+const AGG_PRICE_OFFSET: usize = 208;
+const AGG_CONF_OFFSET: usize = 216;
+const AGG_STATUS_OFFSET: usize = 224;
+const PRICE_ACCOUNT_LEN: usize = 240;
+
+// Widest confidence interval accepted, relative to the price
+pub const MAX_CONFIDENCE_BPS: u64 = 200;
+
+// Decimal places between USD cents and whole dollars, and lamports and SOL
+const CENTS_DECIMALS: i32 = 2;
+const LAMPORTS_DECIMALS: i32 = 9;
+// Widest exponent either way a feed may quote with; keeps every power of ten within u128
+const MAX_EXPONENT: i32 = 18;
+
+// Aggregate SOL/USD price read from a feed account: `price * 10^exponent` dollars per SOL
+#[derive(Clone, Copy, Debug)]
+pub struct PriceFeed {
+    pub price: i64,
+    pub confidence: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+fn read_bytes<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+    let mut bytes = [0u8; N];
+    bytes.copy_from_slice(&data[offset..offset + N]);
+    bytes
+}
+
+impl PriceFeed {
+    pub fn load(info: &AccountInfo) -> Result<Self> {
+        let data = info.try_borrow_data()?;
+        require!(data.len() >= PRICE_ACCOUNT_LEN, CustomError::InvalidPriceFeed);
+        require!(
+            u32::from_le_bytes(read_bytes(&data, MAGIC_OFFSET)) == MAGIC
+                && u32::from_le_bytes(read_bytes(&data, VERSION_OFFSET)) == VERSION
+                && u32::from_le_bytes(read_bytes(&data, ACCOUNT_TYPE_OFFSET)) == PRICE_ACCOUNT_TYPE,
+            CustomError::InvalidPriceFeed
+        );
+        require!(
+            u32::from_le_bytes(read_bytes(&data, AGG_STATUS_OFFSET)) == TRADING_STATUS,
+            CustomError::PriceFeedUnavailable
+        );
+
+        Ok(Self {
+            price: i64::from_le_bytes(read_bytes(&data, AGG_PRICE_OFFSET)),
+            confidence: u64::from_le_bytes(read_bytes(&data, AGG_CONF_OFFSET)),
+            exponent: i32::from_le_bytes(read_bytes(&data, EXPONENT_OFFSET)),
+            publish_time: i64::from_le_bytes(read_bytes(&data, PUBLISH_TIME_OFFSET)),
+        })
+    }
+
+    // Price that is recent enough and precise enough to sell at
+    pub fn checked_price(&self, now: i64, max_age: u64) -> Result<u64> {
+        // Publish times slightly ahead of the cluster clock count as fresh
+        let age = now.saturating_sub(self.publish_time);
+        require!(
+            age <= i64::try_from(max_age).unwrap_or(i64::MAX),
+            CustomError::StalePriceFeed
+        );
+        let price = u64::try_from(self.price)
+            .ok()
+            .filter(|price| *price > 0)
+            .ok_or(CustomError::PriceFeedUnavailable)?;
+        require!(
+            (self.confidence as u128) * (MAX_BPS as u128)
+                <= (price as u128) * (MAX_CONFIDENCE_BPS as u128),
+            CustomError::PriceFeedUnavailable
+        );
+        Ok(price)
+    }
+
+    // Lamports worth `usd_cents`, rounded up so the company never receives less than the USD price
+    pub fn usd_cents_to_lamports(&self, usd_cents: u64, now: i64, max_age: u64) -> Result<u64> {
+        let price = self.checked_price(now, max_age)? as u128;
+        // The exponent comes from the feed account, so it's bounded before any arithmetic
+        require!(
+            (-MAX_EXPONENT..=MAX_EXPONENT).contains(&self.exponent),
+            CustomError::InvalidPriceFeed
+        );
+        // lamports = cents / 10^2 / (price * 10^exponent) * 10^9
+        let scale = (LAMPORTS_DECIMALS - CENTS_DECIMALS)
+            .checked_sub(self.exponent)
+            .ok_or(CustomError::InvalidPriceFeed)?;
+        let (numerator, denominator) = if scale >= 0 {
+            let factor = 10u128
+                .checked_pow(scale as u32)
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+            (
+                (usd_cents as u128)
+                    .checked_mul(factor)
+                    .ok_or(ProgramError::ArithmeticOverflow)?,
+                price,
+            )
+        } else {
+            let factor = 10u128
+                .checked_pow(scale.unsigned_abs())
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+            (
+                usd_cents as u128,
+                price
+                    .checked_mul(factor)
+                    .ok_or(ProgramError::ArithmeticOverflow)?,
+            )
+        };
+        let lamports = numerator.div_ceil(denominator);
+        u64::try_from(lamports).map_err(|_| ProgramError::ArithmeticOverflow.into())
+    }
+}
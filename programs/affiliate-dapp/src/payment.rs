@@ -0,0 +1,530 @@
+use anchor_lang::{prelude::*, solana_program::program::invoke};
+use anchor_spl::{
+    associated_token::{create_idempotent, Create},
+    token_2022::{
+        self,
+        spl_token_2022::{
+            extension::{
+                transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions,
+            },
+            state::Mint as MintState,
+        },
+    },
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    error::CustomError,
+    oracle::PriceFeed,
+    metadata::{check_creator_accounts, Royalty},
+    state::{bps_share, CampaignPriceTable, NFTCampaign, ProtocolConfig},
+};
+
+// Accounts every purchase uses to price the sale and move the buyer's payment.
+// The ones tied to the campaign are checked against it in charge_sale.
+#[derive(Accounts)]
+pub struct SaleAccounts<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    // Funds account creation and rent so a sponsor can onboard buyers; usually the buyer itself
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Company wallet receiving its share of lamport sales
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"marketplace_authority"],
+        bump = marketplace_authority.bump,
+        constraint = !marketplace_authority.paused @ CustomError::ProtocolPaused,
+    )]
+    pub marketplace_authority: Box<Account<'info, ProtocolConfig>>,
+    #[account(
+        mut,
+        address = marketplace_authority.treasury @ CustomError::InvalidTreasury,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    // Extra currencies the campaign accepts, required when paying in one of them
+    pub price_table: Option<Box<Account<'info, CampaignPriceTable>>>,
+    /// CHECK: Parsed as a price feed when the campaign is priced in USD
+    pub price_feed: Option<UncheckedAccount<'info>>,
+
+    // Only when paying in a token; must be the mint of the chosen currency
+    pub payment_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    #[account(
+        mut,
+        token::mint = payment_mint,
+        token::authority = buyer,
+        token::token_program = payment_token_program,
+    )]
+    pub buyer_payment_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        token::mint = payment_mint,
+        token::authority = marketplace_authority.treasury,
+        token::token_program = payment_token_program,
+    )]
+    pub treasury_payment_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        token::mint = payment_mint,
+        token::token_program = payment_token_program,
+    )]
+    pub company_payment_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    pub payment_token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+impl<'info> SaleAccounts<'info> {
+    // The accounts the composite can't tie to the campaign through constraints
+    fn check_campaign_accounts(&self, campaign: &Account<'info, NFTCampaign>) -> Result<()> {
+        require_keys_eq!(self.owner.key(), campaign.company, CustomError::InvalidAccountOwner);
+        if let Some(price_table) = &self.price_table {
+            require_keys_eq!(price_table.campaign, campaign.key(), CustomError::UnsupportedCurrency);
+        }
+        if let Some(price_feed) = &self.price_feed {
+            require_keys_eq!(price_feed.key(), campaign.price_feed, CustomError::InvalidPriceFeed);
+        }
+        if let Some(company_payment_account) = &self.company_payment_account {
+            require_keys_eq!(
+                company_payment_account.owner,
+                campaign.company,
+                CustomError::InvalidAccountOwner
+            );
+        }
+        Ok(())
+    }
+}
+
+// What the buyer asked for, and where the commission goes
+pub struct SaleOrder<'a, 'info> {
+    pub currency: Option<Pubkey>,
+    pub max_total_price: u64,
+    pub quantity: u64,
+    pub commission_bps: u16,
+    // Royalty terms of each NFT of the purchase, in order; empty when none can carry any
+    pub royalties: &'a [Option<Royalty>],
+    // Takes the company's share in place of the company wallet until the NFT, and so its
+    // royalties, is known; random draws only, which sell in lamports
+    pub held_by: Option<AccountInfo<'info>>,
+    // Affiliate vault and, for token sales, its ATA of the payment mint, created on
+    // first use; None for direct sales
+    pub affiliate_vault: Option<AccountInfo<'info>>,
+    pub vault_payment_account: Option<AccountInfo<'info>>,
+}
+
+// What a purchase charged: the currency, the unit price and how the total was split
+pub struct Charge {
+    pub currency: Pubkey,
+    pub price: u64,
+    pub split: SaleSplit,
+}
+
+// How the price of a sale is divided between the protocol, the affiliate, the NFT's
+// creators and the company
+#[derive(Clone, Debug)]
+pub struct SaleSplit {
+    pub protocol_fee: u64,
+    pub commission_amount: u64,
+    pub project_amount: u64,
+    pub royalty_amounts: Vec<u64>, // One per creator, in metadata order
+}
+
+impl SaleSplit {
+    // The protocol fee comes off the top, the affiliate commission applies to what is left
+    pub fn new(price: u64, protocol_fee_bps: u16, commission_bps: u16) -> Result<Self> {
+        let protocol_fee = bps_share(price, protocol_fee_bps)?;
+        let sale_amount = price
+            .checked_sub(protocol_fee)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        let commission_amount = bps_share(sale_amount, commission_bps)?;
+        let project_amount = sale_amount
+            .checked_sub(commission_amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        Ok(Self {
+            protocol_fee,
+            commission_amount,
+            project_amount,
+            royalty_amounts: Vec::new(),
+        })
+    }
+
+    // Creator royalties are paid out of the company's share, so the buyer's price
+    // and the affiliate commission stay the same
+    pub fn with_royalty(mut self, royalty: Option<&Royalty>) -> Result<Self> {
+        let Some(royalty) = royalty else {
+            return Ok(self);
+        };
+        let sale_amount = self
+            .commission_amount
+            .checked_add(self.project_amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        self.royalty_amounts = royalty.creator_amounts(sale_amount)?;
+        let total = self
+            .royalty_amounts
+            .iter()
+            .try_fold(0u64, |total, amount| total.checked_add(*amount))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        self.project_amount = self
+            .project_amount
+            .checked_sub(total)
+            .ok_or(CustomError::RoyaltyExceedsProceeds)?;
+        Ok(self)
+    }
+
+    // Split of a purchase of `quantity` NFTs at this unit split, each paying the royalties
+    // of its own metadata. `royalties` is empty or has one entry per NFT.
+    pub fn for_nfts(&self, quantity: u64, royalties: &[Option<Royalty>]) -> Result<Self> {
+        require!(
+            royalties.is_empty() || royalties.len() as u64 == quantity,
+            CustomError::InvalidRemainingAccounts
+        );
+        let mut total = self.times(quantity)?;
+        for royalty in royalties {
+            let with_royalty = self.clone().with_royalty(royalty.as_ref())?;
+            total.project_amount = total
+                .project_amount
+                .checked_sub(self.project_amount - with_royalty.project_amount)
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+            total.royalty_amounts.extend(with_royalty.royalty_amounts);
+        }
+        Ok(total)
+    }
+
+    // Split of a purchase of `quantity` NFTs at the same unit price
+    pub fn times(&self, quantity: u64) -> Result<Self> {
+        let scale = |amount: u64| {
+            amount
+                .checked_mul(quantity)
+                .ok_or(ProgramError::ArithmeticOverflow)
+        };
+        Ok(Self {
+            protocol_fee: scale(self.protocol_fee)?,
+            commission_amount: scale(self.commission_amount)?,
+            project_amount: scale(self.project_amount)?,
+            royalty_amounts: self
+                .royalty_amounts
+                .iter()
+                .map(|amount| scale(*amount))
+                .collect::<std::result::Result<_, _>>()?,
+        })
+    }
+}
+
+// Currency and price of a purchase: the campaign's own price unless the buyer
+// picked another currency from the campaign's price table. The own price is the
+// current auction price while one is set, mint_price otherwise. USD-priced campaigns
+// convert it to lamports with their price feed.
+pub fn resolve_price(
+    campaign: &NFTCampaign,
+    price_table: Option<&CampaignPriceTable>,
+    price_feed: Option<&AccountInfo>,
+    currency: Option<Pubkey>,
+) -> Result<(Pubkey, u64)> {
+    let now = Clock::get()?.unix_timestamp;
+    match currency {
+        Some(currency) if currency != campaign.payment_mint => {
+            // Fixed table prices would undercut the falling auction price
+            require!(campaign.auction.is_none(), CustomError::AuctionCurrencyOnly);
+            let price = price_table
+                .and_then(|table| table.price_of(&currency))
+                .ok_or(CustomError::UnsupportedCurrency)?;
+            Ok((currency, price))
+        }
+        _ => {
+            let price = match &campaign.auction {
+                Some(auction) => auction.price_at(now)?,
+                None => campaign.mint_price,
+            };
+            if campaign.price_feed == Pubkey::default() {
+                return Ok((campaign.payment_mint, price));
+            }
+            let price_feed = price_feed.ok_or(CustomError::MissingPriceFeed)?;
+            let lamports = PriceFeed::load(price_feed)?.usd_cents_to_lamports(
+                price,
+                now,
+                campaign.max_price_age,
+            )?;
+            Ok((campaign.payment_mint, lamports))
+        }
+    }
+}
+
+// System transfer from a wallet that signed the transaction; zero amounts are skipped
+pub fn transfer_lamports<'info>(
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let transfer_ix =
+        anchor_lang::solana_program::system_instruction::transfer(from.key, to.key, amount);
+    invoke(&transfer_ix, &[from.clone(), to.clone(), system_program.clone()])?;
+    Ok(())
+}
+
+// A royalty too small to make an unfunded creator wallet rent exempt would fail the
+// whole sale, so the payer covers the difference before `amount` is sent
+pub fn fund_rent_shortfall<'info>(
+    payer: &AccountInfo<'info>,
+    creator: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let shortfall = Rent::get()?
+        .minimum_balance(creator.data_len())
+        .saturating_sub(creator.lamports().saturating_add(amount));
+    transfer_lamports(payer, creator, system_program, shortfall)
+}
+
+// Pays out a lamport sale: fee to the treasury, commission to the affiliate vault,
+// royalties to the creator wallets, rest to the company
+pub fn pay_sale<'info>(
+    buyer: &AccountInfo<'info>,
+    treasury: &AccountInfo<'info>,
+    affiliate_vault: Option<&AccountInfo<'info>>,
+    company: &AccountInfo<'info>,
+    creators: &[AccountInfo<'info>],
+    system_program: &AccountInfo<'info>,
+    split: &SaleSplit,
+) -> Result<()> {
+    transfer_lamports(buyer, treasury, system_program, split.protocol_fee)?;
+    if let Some(affiliate_vault) = affiliate_vault {
+        transfer_lamports(buyer, affiliate_vault, system_program, split.commission_amount)?;
+    }
+    for (creator, amount) in creators.iter().zip(&split.royalty_amounts) {
+        transfer_lamports(buyer, creator, system_program, *amount)?;
+    }
+    transfer_lamports(buyer, company, system_program, split.project_amount)?;
+    Ok(())
+}
+
+// Token accounts used when a campaign is priced in an SPL token instead of lamports
+pub struct TokenSaleAccounts<'info> {
+    pub buyer: AccountInfo<'info>,
+    pub payment_mint: AccountInfo<'info>,
+    pub decimals: u8,
+    pub buyer_payment_account: AccountInfo<'info>,
+    pub treasury_payment_account: AccountInfo<'info>,
+    pub vault_payment_account: Option<AccountInfo<'info>>, // None when there is no commission
+    pub company_payment_account: AccountInfo<'info>,
+    pub payment_token_program: AccountInfo<'info>,
+}
+
+impl<'info> TokenSaleAccounts<'info> {
+    // Gathers the optional payment accounts of a purchase, all of which are required together
+    fn from_sale(
+        sale: &SaleAccounts<'info>,
+        vault_payment_account: Option<AccountInfo<'info>>,
+    ) -> Result<Self> {
+        let missing = || error!(CustomError::MissingPaymentAccounts);
+        let payment_mint = sale.payment_mint.as_deref().ok_or_else(missing)?;
+        Ok(Self {
+            buyer: sale.buyer.to_account_info(),
+            payment_mint: payment_mint.to_account_info(),
+            decimals: payment_mint.decimals,
+            buyer_payment_account: sale.buyer_payment_account.as_deref().ok_or_else(missing)?.to_account_info(),
+            treasury_payment_account: sale.treasury_payment_account.as_deref().ok_or_else(missing)?.to_account_info(),
+            vault_payment_account,
+            company_payment_account: sale.company_payment_account.as_deref().ok_or_else(missing)?.to_account_info(),
+            payment_token_program: sale.payment_token_program.as_ref().ok_or_else(missing)?.to_account_info(),
+        })
+    }
+}
+
+// Amount to send so that `amount` arrives after the mint's Token-2022 transfer fee, if any
+pub fn gross_up_for_transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    if amount == 0 || *mint.owner != token_2022::ID {
+        return Ok(amount);
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data)?;
+    let Ok(fee_config) = state.get_extension::<TransferFeeConfig>() else {
+        return Ok(amount);
+    };
+    let gross = fee_config
+        .get_epoch_fee(Clock::get()?.epoch)
+        .calculate_pre_fee_amount(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    // A 100% fee can never deliver anything
+    require!(gross >= amount, CustomError::InvalidPaymentMint);
+    Ok(gross)
+}
+
+// transfer_checked from a wallet that signed the transaction, grossed up so the
+// recipient receives exactly `amount`; zero amounts are skipped
+pub fn transfer_tokens<'info>(
+    accounts: &TokenSaleAccounts<'info>,
+    to: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let gross_amount = gross_up_for_transfer_fee(&accounts.payment_mint, amount)?;
+    transfer_checked(
+        CpiContext::new(
+            accounts.payment_token_program.clone(),
+            TransferChecked {
+                from: accounts.buyer_payment_account.clone(),
+                mint: accounts.payment_mint.clone(),
+                to: to.clone(),
+                authority: accounts.buyer.clone(),
+            },
+        ),
+        gross_amount,
+        accounts.decimals,
+    )
+}
+
+// Everything the buyer is charged for a split: the price itself, plus the transfer
+// fees added on top when paying with a Token-2022 fee mint
+pub fn sale_cost(token_sale: Option<&TokenSaleAccounts>, split: &SaleSplit) -> Result<u64> {
+    let shares = [split.protocol_fee, split.commission_amount, split.project_amount];
+    shares.iter().chain(&split.royalty_amounts).try_fold(0u64, |total, share| {
+        let charged = match token_sale {
+            Some(token_sale) => gross_up_for_transfer_fee(&token_sale.payment_mint, *share)?,
+            None => *share,
+        };
+        total
+            .checked_add(charged)
+            .ok_or_else(|| ProgramError::ArithmeticOverflow.into())
+    })
+}
+
+// Same split as pay_sale, paid in the `currency` token with royalties going to the
+// creators' token accounts. Transfer fees are charged on top so every recipient
+// receives its full share.
+pub fn pay_sale_in_tokens<'info>(
+    accounts: &TokenSaleAccounts<'info>,
+    currency: Pubkey,
+    creators: &[AccountInfo<'info>],
+    split: &SaleSplit,
+) -> Result<()> {
+    require_keys_eq!(accounts.payment_mint.key(), currency, CustomError::InvalidPaymentMint);
+    transfer_tokens(accounts, &accounts.treasury_payment_account, split.protocol_fee)?;
+    if let Some(vault_payment_account) = &accounts.vault_payment_account {
+        transfer_tokens(accounts, vault_payment_account, split.commission_amount)?;
+    }
+    for (creator, amount) in creators.iter().zip(&split.royalty_amounts) {
+        transfer_tokens(accounts, creator, *amount)?;
+    }
+    transfer_tokens(accounts, &accounts.company_payment_account, split.project_amount)?;
+    Ok(())
+}
+
+// Prices a purchase of `order.quantity` NFTs and collects it from the buyer: the protocol
+// fee to the treasury, the commission to the affiliate vault, royalties to the creator
+// accounts and the rest to the company. Nothing moves if the sale costs more than the
+// buyer signed for. Rent for creator accounts that can't take their royalty yet is on
+// the payer, outside the buyer's max total price.
+pub fn charge_sale<'info>(
+    sale: &SaleAccounts<'info>,
+    campaign: &Account<'info, NFTCampaign>,
+    associated_token_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    creator_accounts: &[AccountInfo<'info>],
+    order: SaleOrder<'_, 'info>,
+) -> Result<Charge> {
+    sale.check_campaign_accounts(campaign)?;
+    let (currency, price) = resolve_price(
+        campaign,
+        sale.price_table.as_deref().map(|table| &**table),
+        sale.price_feed.as_ref().map(|feed| feed.as_ref()),
+        order.currency,
+    )?;
+    let split = SaleSplit::new(price, sale.marketplace_authority.protocol_fee_bps, order.commission_bps)?
+        .for_nfts(order.quantity, order.royalties)?;
+    require!(
+        order.held_by.is_none() || currency == Pubkey::default(),
+        CustomError::RandomDrawCurrency
+    );
+
+    let token_sale = if currency == Pubkey::default() {
+        None
+    } else {
+        let token_sale = TokenSaleAccounts::from_sale(sale, order.vault_payment_account)?;
+        match (&order.affiliate_vault, &token_sale.vault_payment_account) {
+            (Some(affiliate_vault), Some(vault_payment_account)) => {
+                create_idempotent(CpiContext::new(
+                    associated_token_program.clone(),
+                    Create {
+                        payer: sale.payer.to_account_info(),
+                        associated_token: vault_payment_account.clone(),
+                        authority: affiliate_vault.clone(),
+                        mint: token_sale.payment_mint.clone(),
+                        system_program: system_program.clone(),
+                        token_program: token_sale.payment_token_program.clone(),
+                    },
+                ))?;
+            }
+            (None, None) => {}
+            _ => return err!(CustomError::MissingPaymentAccounts),
+        }
+        Some(token_sale)
+    };
+    check_creator_accounts(order.royalties, creator_accounts, token_sale.as_ref())?;
+    require!(
+        sale_cost(token_sale.as_ref(), &split)? <= order.max_total_price,
+        CustomError::MaxTotalPriceExceeded
+    );
+    match &token_sale {
+        Some(token_sale) => {
+            let mut creator_token_accounts = Vec::with_capacity(split.royalty_amounts.len());
+            for (accounts, amount) in creator_accounts.chunks(2).zip(&split.royalty_amounts) {
+                let [creator, creator_token_account] = accounts else {
+                    return err!(CustomError::InvalidRemainingAccounts);
+                };
+                // Creators that never held the payment mint get an ATA, paid for by the payer
+                if *amount > 0 {
+                    create_idempotent(CpiContext::new(
+                        associated_token_program.clone(),
+                        Create {
+                            payer: sale.payer.to_account_info(),
+                            associated_token: creator_token_account.clone(),
+                            authority: creator.clone(),
+                            mint: token_sale.payment_mint.clone(),
+                            system_program: system_program.clone(),
+                            token_program: token_sale.payment_token_program.clone(),
+                        },
+                    ))?;
+                }
+                creator_token_accounts.push(creator_token_account.clone());
+            }
+            pay_sale_in_tokens(token_sale, currency, &creator_token_accounts, &split)?
+        }
+        None => {
+            for (creator, amount) in creator_accounts.iter().zip(&split.royalty_amounts) {
+                fund_rent_shortfall(&sale.payer.to_account_info(), creator, system_program, *amount)?;
+            }
+            let company = match &order.held_by {
+                Some(held_by) => held_by.clone(),
+                None => sale.owner.to_account_info(),
+            };
+            pay_sale(
+                &sale.buyer.to_account_info(),
+                &sale.treasury.to_account_info(),
+                order.affiliate_vault.as_ref(),
+                &company,
+                creator_accounts,
+                system_program,
+                &split,
+            )?
+        }
+    }
+
+    Ok(Charge {
+        currency,
+        price,
+        split,
+    })
+}
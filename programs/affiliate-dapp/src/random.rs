@@ -0,0 +1,84 @@
+use anchor_lang::{prelude::*, solana_program::hash::hashv};
+
+use crate::error::CustomError;
+
+// SlotHashes sysvar layout: a u64 entry count, then (slot: u64, hash: [u8; 32]) newest first.
+// Entries are read in place, so the ~20KB sysvar is never deserialized.
+const SLOT_HASHES_LEN_SIZE: usize = 8;
+const SLOT_HASH_ENTRY_SIZE: usize = 8 + 32;
+
+// Everything that went into a draw, emitted so anyone can recompute the index
+#[derive(Clone, Copy, Debug)]
+pub struct DrawSeed {
+    pub slot: u64,
+    pub slot_hash: [u8; 32],
+    pub seed: [u8; 32],
+}
+
+// Where a draw stands against the SlotHashes window
+pub enum DrawSlotHash {
+    // The hash of the first slot after the purchase
+    Ready { slot: u64, hash: [u8; 32] },
+    // No slot after the purchase has a hash yet
+    NotReady,
+    // The window moved past the purchase slot, so the hash to draw from is gone
+    Expired,
+}
+
+// Finds the hash of the first slot after `draw_slot`. Nobody can know it when the
+// purchase is sent, and it's still in the window only for ~512 slots after.
+pub fn find_draw_slot_hash(slot_hashes: &AccountInfo, draw_slot: u64) -> Result<DrawSlotHash> {
+    let data = slot_hashes.try_borrow_data()?;
+    require!(data.len() >= SLOT_HASHES_LEN_SIZE, CustomError::InvalidSlotHashes);
+    let mut len = [0u8; SLOT_HASHES_LEN_SIZE];
+    len.copy_from_slice(&data[..SLOT_HASHES_LEN_SIZE]);
+    let len = usize::try_from(u64::from_le_bytes(len)).map_err(|_| CustomError::InvalidSlotHashes)?;
+    let entries = &data[SLOT_HASHES_LEN_SIZE..];
+    require!(
+        len > 0 && entries.len() >= len.saturating_mul(SLOT_HASH_ENTRY_SIZE),
+        CustomError::InvalidSlotHashes
+    );
+
+    let mut found = None;
+    for entry in entries.chunks_exact(SLOT_HASH_ENTRY_SIZE).take(len) {
+        let mut slot = [0u8; 8];
+        slot.copy_from_slice(&entry[..8]);
+        let slot = u64::from_le_bytes(slot);
+        if slot <= draw_slot {
+            // Entries are newest first, so the last one seen after draw_slot is the first slot after it
+            return Ok(match found {
+                Some((slot, hash)) => DrawSlotHash::Ready { slot, hash },
+                None => DrawSlotHash::NotReady,
+            });
+        }
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&entry[8..]);
+        found = Some((slot, hash));
+    }
+    // Every entry is after draw_slot: slots right after it may have dropped out of the window
+    Ok(DrawSlotHash::Expired)
+}
+
+// Seed of a draw: sha256(slot_hash || buyer || sequence as little-endian u64)
+pub fn draw_seed(slot_hashes: &AccountInfo, draw_slot: u64, buyer: &Pubkey, sequence: u64) -> Result<DrawSeed> {
+    let (slot, slot_hash) = match find_draw_slot_hash(slot_hashes, draw_slot)? {
+        DrawSlotHash::Ready { slot, hash } => (slot, hash),
+        DrawSlotHash::NotReady => return err!(CustomError::DrawNotReady),
+        DrawSlotHash::Expired => return err!(CustomError::DrawExpired),
+    };
+
+    let seed = hashv(&[&slot_hash, buyer.as_ref(), &sequence.to_le_bytes()]).to_bytes();
+    Ok(DrawSeed {
+        slot,
+        slot_hash,
+        seed,
+    })
+}
+
+// Index into a list of `len` entries: the seed's first 8 bytes as a little-endian u64, mod len
+pub fn draw_index(seed: &[u8; 32], len: usize) -> Result<usize> {
+    require!(len > 0, CustomError::SoldOut);
+    let mut head = [0u8; 8];
+    head.copy_from_slice(&seed[..8]);
+    Ok((u64::from_le_bytes(head) % len as u64) as usize)
+}
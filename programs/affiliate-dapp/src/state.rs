@@ -1,4 +1,9 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{
+    prelude::*,
+    system_program::{allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer},
+};
+
+use crate::error::CustomError;
 //use anchor_lang::solana_program::pubkey::Pubkey;
 
 #[account]
@@ -8,14 +13,103 @@ pub struct NFTCampaign {
     pub company: Pubkey,              // Campaign creator
     #[max_len(32)]
     pub name: String,                 // Campaign name
-    pub mint_price: u64,              // Price to mint one NFT
-    pub commission_percentage: u8,    // Commission percentage (0-100)
+    pub mint_price: u64,              // Price to mint one NFT, in lamports, base units of payment_mint, or USD cents with a price_feed
+    pub commission_bps: u16,          // Commission in basis points (0-10000)
     #[max_len(200)]
     pub campaign_details: String,     // Additional details
     pub active: bool,                 // Campaign status
     pub affiliates_count: u64,        // Number of affiliates
     pub total_mints: u64,             // Total NFTs minted via affiliates
     pub created_at: i64,             // Timestamp of campaign creation
+    pub available_supply: u64,        // NFTs currently held in the campaign escrow
+    pub token_program: Pubkey,        // SPL Token or Token-2022 program used by the campaign NFTs
+    pub mode: CampaignMode,           // Sell escrowed NFTs or mint new ones on purchase
+    #[max_len(200)]
+    pub base_uri: String,             // Metadata URI prefix for fresh-mint NFTs
+    pub fresh_mint_count: u64,        // NFTs minted on purchase, used as their sequence number
+    pub group_mint: Pubkey,           // Token-2022 group the campaign NFTs must belong to; default when unset
+    pub payment_mint: Pubkey,         // SPL token buyers pay with; default when priced in lamports
+    pub price_feed: Pubkey,           // SOL/USD feed converting a USD mint_price to lamports; default when unset
+    pub max_price_age: u64,           // Oldest feed price accepted, in seconds
+    pub direct_sales: u64,            // NFTs sold without an affiliate link
+    pub collection_mint: Pubkey,      // Verified collection escrowed NFTs must belong to; default when unset
+    pub selection_mode: SelectionMode, // Whether buyers pick the escrowed NFT or get a random one
+    pub pending_draws: u64,           // Random draws paid for but not yet delivered from the escrow
+    pub auction: Option<DutchAuction>, // Falling price used instead of mint_price; None for a fixed price
+    pub legacy_campaign: Pubkey,      // Name-keyed campaign this one was migrated from; default otherwise
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum CampaignMode {
+    Escrow,    // Sales transfer NFTs the company deposited into the escrow
+    FreshMint, // Sales mint a new Token-2022 NFT with the campaign PDA as mint authority
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum SelectionMode {
+    BuyerChoice, // Buyers name the escrowed mint they purchase
+    Random,      // Purchases draw from the campaign inventory list
+}
+
+// Price falling linearly from start_price at start_time to end_price decay_interval seconds later,
+// in the same unit as mint_price
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub struct DutchAuction {
+    pub start_price: u64,        // Price when sales open
+    pub end_price: u64,          // Floor the price stays at once the decay interval is over
+    pub start_time: i64,         // Unix timestamp sales open at
+    pub decay_interval: i64,     // Seconds the price takes to fall from start_price to end_price
+}
+
+impl DutchAuction {
+    pub fn validate(&self) -> Result<()> {
+        require!(self.end_price > 0, CustomError::InvalidPrice);
+        require!(
+            self.start_price > self.end_price && self.decay_interval > 0,
+            CustomError::InvalidAuction
+        );
+        Ok(())
+    }
+
+    // Price at unix time `now`; the decrease is rounded down so the price never undershoots
+    pub fn price_at(&self, now: i64) -> Result<u64> {
+        require!(now >= self.start_time, CustomError::AuctionNotStarted);
+        let elapsed = now.saturating_sub(self.start_time).min(self.decay_interval);
+        let decrease = ((self.start_price - self.end_price) as u128)
+            .checked_mul(elapsed as u128)
+            .ok_or(ProgramError::ArithmeticOverflow)?
+            / self.decay_interval as u128;
+        Ok(self.start_price - decrease as u64)
+    }
+}
+
+// What happens to the company's authority over an escrowed NFT's mint when the campaign is created
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MintAuthorityLock {
+    Keep,     // The company keeps the mint authority
+    Revoke,   // Nobody can ever mint again
+    Campaign, // Held by the campaign's mint_authority PDA, which never mints escrowed NFTs
+}
+
+// Basis points in 100%
+pub const MAX_BPS: u16 = 10_000;
+
+// Share of `amount` for a rate in basis points; u128 math so fractional percents never overflow
+pub fn bps_share(amount: u64, bps: u16) -> Result<u64> {
+    let share = (amount as u128)
+        .checked_mul(bps as u128)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .checked_div(MAX_BPS as u128)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    u64::try_from(share).map_err(|_| ProgramError::ArithmeticOverflow.into())
+}
+
+impl NFTCampaign {
+    // Account size for a campaign whose strings are sized to their actual length.
+    // INIT_SPACE reserves each string's max_len, so swap those for the real lengths.
+    pub fn space(name_len: usize, campaign_details_len: usize, base_uri_len: usize) -> usize {
+        8 + NFTCampaign::INIT_SPACE - (32 + 200 + 200) + name_len + campaign_details_len + base_uri_len
+    }
 }
 
 #[account]
@@ -24,7 +118,284 @@ pub struct AffiliateLink {
     pub campaign: Pubkey,        // Campaign this affiliate link belongs to
     pub influencer: Pubkey,      // Wallet of the influencer
     pub mints_count: u64,        // Number of mints through this link
-    pub earnings: u64,           // Total earnings in lamports
+    pub earnings: u64,           // Total earnings in lamports or base units of payment_mint
+    pub claimed_earnings: u64,   // Earnings already withdrawn from the affiliate vault
+    pub unclaimed_earnings: u64, // Earnings waiting in the affiliate vault
    // pub influencer_token_account: Pubkey, // Token account of the influencer
     pub created_at: i64,         // Timestamp of link creation
-}
\ No newline at end of file
+    pub payment_mint: Pubkey,    // Campaign payment mint when the link was created; default for lamports
+}
+
+impl AffiliateLink {
+    // Books a sale commission on the link itself when paid in the campaign's payment currency,
+    // otherwise on the link's AffiliateEarnings account for that price-table currency
+    pub fn record_commission(
+        &mut self,
+        affiliate_link: Pubkey,
+        currency: Pubkey,
+        currency_earnings: Option<&mut AffiliateEarnings>,
+        amount: u64,
+    ) -> Result<()> {
+        let (earnings, unclaimed_earnings) = if currency == self.payment_mint {
+            (&mut self.earnings, &mut self.unclaimed_earnings)
+        } else {
+            let currency_earnings = currency_earnings.ok_or(CustomError::MissingCurrencyEarnings)?;
+            if currency_earnings.affiliate_link == Pubkey::default() {
+                currency_earnings.affiliate_link = affiliate_link;
+                currency_earnings.currency = currency;
+            }
+            (
+                &mut currency_earnings.earnings,
+                &mut currency_earnings.unclaimed_earnings,
+            )
+        };
+        *earnings = earnings
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        *unclaimed_earnings = unclaimed_earnings
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        Ok(())
+    }
+}
+
+// Commissions of an affiliate link in one of the campaign's price-table currencies
+#[account]
+#[derive(InitSpace,Debug)]
+pub struct AffiliateEarnings {
+    pub affiliate_link: Pubkey,  // Link these earnings belong to
+    pub currency: Pubkey,        // Payment mint; default for lamports
+    pub earnings: u64,           // Total earnings in this currency
+    pub claimed_earnings: u64,   // Earnings already withdrawn from the affiliate vault
+    pub unclaimed_earnings: u64, // Earnings waiting in the affiliate vault
+}
+
+// Most currencies a price table can list
+pub const MAX_PRICE_TABLE_ENTRIES: usize = 8;
+
+// Currencies a campaign accepts in addition to its own payment currency
+#[account]
+#[derive(InitSpace,Debug)]
+pub struct CampaignPriceTable {
+    pub campaign: Pubkey,        // Campaign the prices apply to
+    #[max_len(MAX_PRICE_TABLE_ENTRIES)]
+    pub prices: Vec<CurrencyPrice>,
+    pub bump: u8,                // Bump of the [b"price_table", campaign] PDA
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub struct CurrencyPrice {
+    pub mint: Pubkey,            // Payment mint; default for lamports
+    pub price: u64,              // Price in lamports or base units of mint
+}
+
+impl CampaignPriceTable {
+    pub fn price_of(&self, currency: &Pubkey) -> Option<u64> {
+        self.prices
+            .iter()
+            .find(|entry| entry.mint == *currency)
+            .map(|entry| entry.price)
+    }
+}
+
+// Most escrowed NFTs a random-selection campaign can list at once
+pub const MAX_INVENTORY_ENTRIES: usize = 128;
+
+// Escrowed NFTs a random-selection campaign draws from, one entry per token held
+#[account]
+#[derive(InitSpace,Debug)]
+pub struct CampaignInventory {
+    pub campaign: Pubkey,        // Campaign the inventory belongs to
+    #[max_len(MAX_INVENTORY_ENTRIES)]
+    pub mints: Vec<Pubkey>,
+    pub max_royalty_bps: u16,    // Highest seller fee ever listed; the commission must leave room for it
+    pub bump: u8,                // Bump of the [b"inventory", campaign] PDA
+}
+
+impl CampaignInventory {
+    pub fn add(&mut self, mint: Pubkey, amount: u64) -> Result<()> {
+        let amount = usize::try_from(amount).map_err(|_| CustomError::InventoryFull)?;
+        require!(
+            self.mints.len().saturating_add(amount) <= MAX_INVENTORY_ENTRIES,
+            CustomError::InventoryFull
+        );
+        self.mints.resize(self.mints.len() + amount, mint);
+        Ok(())
+    }
+
+    // Draws hold the company share of the sale, which has to cover the drawn NFT's royalties.
+    // Both are shares of the price after the protocol fee, so listing a seller fee is only
+    // allowed while it fits beside the commission.
+    pub fn add_royalty(&mut self, seller_fee_bps: u16, commission_bps: u16) -> Result<()> {
+        self.max_royalty_bps = self.max_royalty_bps.max(seller_fee_bps);
+        self.require_royalty_covered(commission_bps)
+    }
+
+    pub fn require_royalty_covered(&self, commission_bps: u16) -> Result<()> {
+        require!(
+            u32::from(self.max_royalty_bps) + u32::from(commission_bps) <= u32::from(MAX_BPS),
+            CustomError::RoyaltyExceedsProceeds
+        );
+        Ok(())
+    }
+
+    // Order doesn't matter to the draw, so entries are swap-removed
+    pub fn remove(&mut self, mint: &Pubkey, amount: u64) -> Result<()> {
+        for _ in 0..amount {
+            let index = self
+                .mints
+                .iter()
+                .position(|entry| entry == mint)
+                .ok_or(CustomError::NotInInventory)?;
+            self.mints.swap_remove(index);
+        }
+        Ok(())
+    }
+}
+
+// A random purchase whose NFT still sits in the escrow, waiting for deliver_random_draw
+#[account]
+#[derive(InitSpace,Debug)]
+pub struct RandomDraw {
+    pub campaign: Pubkey,        // Campaign the NFT is drawn from
+    pub buyer: Pubkey,           // Signer of the purchase, part of the draw seed
+    pub recipient: Pubkey,       // Wallet the NFT is delivered to
+    pub rent_payer: Pubkey,      // Gets this account's rent back on delivery
+    pub sequence: u64,           // Campaign total_mints before the sale
+    pub draw_slot: u64,          // The NFT is drawn from the hash of the first slot after this one
+    pub sale_amount: u64,        // Price after the protocol fee, what royalties are a share of
+    pub held_amount: u64,        // Company share held here until royalties are paid on delivery
+    pub bump: u8,                // Bump of the [b"random_draw", campaign, sequence] PDA
+}
+
+#[account]
+#[derive(InitSpace,Debug)]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,           // Key allowed to change the config
+    pub treasury: Pubkey,        // Wallet receiving protocol fees
+    pub protocol_fee_bps: u16,   // Fee taken from every sale before the affiliate split
+    pub paused: bool,            // Emergency switch that halts campaigns, links and sales
+    pub bump: u8,                // Bump of the marketplace_authority PDA
+}
+
+// Layouts of accounts created under the old name-keyed PDAs
+// ([b"nft_campaign", name] and [b"affiliate_link", influencer, name]).
+// Kept separate from the live accounts so migrations keep working as those evolve.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug)]
+pub struct LegacyNFTCampaign {
+    pub nft_mint: Pubkey,
+    pub company: Pubkey,
+    pub name: String,
+    pub mint_price: u64,
+    pub commission_percentage: u8,
+    pub campaign_details: String,
+    pub active: bool,
+    pub affiliates_count: u64,
+    pub total_mints: u64,
+    pub created_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug)]
+pub struct LegacyAffiliateLink {
+    pub campaign: Pubkey,
+    pub influencer: Pubkey,
+    pub mints_count: u64,
+    pub earnings: u64,
+    pub created_at: i64,
+}
+
+// Deserializes a legacy account after checking it is owned by this program
+// and carries the discriminator of the account type it was created as.
+pub fn load_legacy_account<T: AnchorDeserialize>(
+    info: &AccountInfo,
+    discriminator: [u8; 8],
+) -> Result<T> {
+    require_keys_eq!(*info.owner, crate::ID, CustomError::InvalidLegacyAccount);
+    let data = info.try_borrow_data()?;
+    require!(
+        data.len() >= 8 && data[..8] == discriminator,
+        CustomError::InvalidLegacyAccount
+    );
+    T::deserialize(&mut &data[8..]).map_err(|_| error!(CustomError::InvalidLegacyAccount))
+}
+
+// Closes a migrated legacy account, sending its rent to `destination`.
+pub fn close_legacy_account<'info>(
+    info: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+) -> Result<()> {
+    let lamports = info.lamports();
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **info.try_borrow_mut_lamports()? = 0;
+
+    info.assign(&System::id());
+    info.realloc(0, false)?;
+    Ok(())
+}
+
+// Creates a program-derived account the way Anchor's `init` does. CreateAccount fails on
+// an address that already holds lamports, and anyone can send some to a predictable PDA,
+// so a funded account is topped up to `lamports` and then allocated and assigned instead.
+pub fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    lamports: u64,
+    space: usize,
+    owner: &Pubkey,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    let current_lamports = account.lamports();
+    if current_lamports == 0 {
+        return create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                &[signer_seeds],
+            ),
+            lamports,
+            space as u64,
+            owner,
+        );
+    }
+
+    let shortfall = lamports.saturating_sub(current_lamports);
+    if shortfall > 0 {
+        transfer(
+            CpiContext::new(
+                system_program.clone(),
+                Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            Allocate {
+                account_to_allocate: account.clone(),
+            },
+            &[signer_seeds],
+        ),
+        space as u64,
+    )?;
+    assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            Assign {
+                account_to_assign: account.clone(),
+            },
+            &[signer_seeds],
+        ),
+        owner,
+    )
+}
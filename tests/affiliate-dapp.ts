@@ -1,26 +1,51 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
-import { PublicKey, SystemProgram, Keypair } from "@solana/web3.js";
+import { PublicKey, SystemProgram, Keypair, ComputeBudgetProgram } from "@solana/web3.js";
 import {
   createInitializeMintInstruction,
   createMintToInstruction,
   getAssociatedTokenAddressSync,
   createAssociatedTokenAccountInstruction,
   TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
   getAccount,
+  getMint,
+  getTokenGroupMemberState,
+  getMintLen,
+  createInitializeGroupMemberPointerInstruction,
+  ExtensionType,
   ASSOCIATED_TOKEN_PROGRAM_ID,
 } from "@solana/spl-token";
 import { assert } from "chai";
+import * as fs from "fs";
+import { createHash } from "crypto";
 import { AffiliateDapp } from "../target/types/affiliate_dapp"; // Replace with your program type
 
 describe("nft-campaign", () => {
   const provider = anchor.AnchorProvider.env();
   anchor.setProvider(provider);
   const program = anchor.workspace.AffiliateDapp as Program<AffiliateDapp>;
+  const [marketplaceAuthorityPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("marketplace_authority")],
+    program.programId
+  );
+  const TOKEN_METADATA_PROGRAM_ID = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+  const metadataPda = (mint: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+      TOKEN_METADATA_PROGRAM_ID
+    )[0];
 
   const campaignName = "test-campaign";
   const mintPrice = new anchor.BN(1_000_000); // 1 SOL in lamports
-  const commissionPercentage = 10;
+  const commissionBps = 750; // 7.5%
+  const protocolFeeBps = 250; // 2.5%
+  // Protocol fee comes off the top, commission applies to the remainder
+  const protocolFee = Math.floor(mintPrice.toNumber() * protocolFeeBps / 10_000);
+  const expectedCommission = Math.floor((mintPrice.toNumber() - protocolFee) * commissionBps / 10_000);
   const campaignDetails = "Test campaign details";
 
   let company = Keypair.generate();
@@ -31,6 +56,109 @@ describe("nft-campaign", () => {
   let escrowTokenAccount: PublicKey;
   let influencer = Keypair.generate();
   let affiliateLinkPda: PublicKey;
+  let affiliateVaultPda: PublicKey;
+  let treasury = Keypair.generate();
+  // Deposited into the main campaign by the inventory tests and withdrawn again
+  let restockedNft: { mint: PublicKey; account: PublicKey };
+
+  const loadKeypair = (path: string) =>
+    Keypair.fromSecretKey(Uint8Array.from(JSON.parse(fs.readFileSync(path, "utf8"))));
+  // NFT held by the company, SPL Token unless told otherwise. A fixture ("royalty" loads
+  // tests/fixtures/royalty_nft_mint.json) pins the mint address so the metadata fixture
+  // carrying its royalties or collection sits at its PDA.
+  const createCompanyNft = async ({
+    fixture,
+    supply = 1,
+    decimals = 0,
+    tokenProgram = TOKEN_PROGRAM_ID,
+  }: { fixture?: string; supply?: number; decimals?: number; tokenProgram?: PublicKey } = {}) => {
+    const mintKeypair = fixture ? loadKeypair(`tests/fixtures/${fixture}_nft_mint.json`) : undefined;
+    const mintKey = await createMint(provider.connection, company, company.publicKey, null, decimals, mintKeypair, undefined, tokenProgram);
+    const account = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      company,
+      mintKey,
+      company.publicKey,
+      false,
+      undefined,
+      undefined,
+      tokenProgram
+    );
+    await mintTo(provider.connection, company, mintKey, account.address, company, supply, [], undefined, tokenProgram);
+    return { mint: mintKey, account: account.address };
+  };
+  // Addresses of a company-scoped campaign and its escrow
+  const campaignAddresses = (name: string, tokenProgram = TOKEN_PROGRAM_ID) => {
+    const [campaign] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from(name)],
+      program.programId
+    );
+    const [escrow] = PublicKey.findProgramAddressSync([Buffer.from("nft_escrow"), campaign.toBuffer()], program.programId);
+    const [mintAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint_authority"), campaign.toBuffer()],
+      program.programId
+    );
+    const escrowAccountFor = (mint: PublicKey) => getAssociatedTokenAddressSync(mint, escrow, true, tokenProgram);
+    return { campaign, escrow, mintAuthority, escrowAccountFor, tokenProgram };
+  };
+  // Escrow campaign selling `nft`, created by the company at the shared price and commission
+  const createEscrowCampaign = async (
+    name: string,
+    nft: { mint: PublicKey; account: PublicKey },
+    { lock = { keep: {} }, tokenProgram = TOKEN_PROGRAM_ID, collectionMint }: {
+      lock?: object;
+      tokenProgram?: PublicKey;
+      collectionMint?: PublicKey;
+    } = {}
+  ) => {
+    const addresses = campaignAddresses(name, tokenProgram);
+    await program.methods
+      .createNftCampaign(name, mintPrice, commissionBps, campaignDetails, lock as any)
+      .accounts({
+        company: company.publicKey,
+        campaign: addresses.campaign,
+        mintAuthority: addresses.mintAuthority,
+        nftMint: nft.mint,
+        projectTokenAccount: nft.account,
+        nftEscrow: addresses.escrow,
+        escrowPdaNftTokenAccount: addresses.escrowAccountFor(nft.mint),
+        marketplaceAuthority: marketplaceAuthorityPda,
+        collectionMint: collectionMint ?? null,
+        nftMetadata: collectionMint ? metadataPda(nft.mint) : null,
+        tokenProgram,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([company])
+      .rpc();
+    return addresses;
+  };
+  // Moves one more company NFT into the escrow of a campaign made by createEscrowCampaign
+  const depositCompanyNft = (
+    addresses: ReturnType<typeof campaignAddresses>,
+    nft: { mint: PublicKey; account: PublicKey }
+  ) =>
+    program.methods
+      .depositInventory(new anchor.BN(1))
+      .accounts({
+        company: company.publicKey,
+        campaign: addresses.campaign,
+        nftMint: nft.mint,
+        nftMetadata: metadataPda(nft.mint),
+        projectTokenAccount: nft.account,
+        nftEscrow: addresses.escrow,
+        escrowPdaNftTokenAccount: addresses.escrowAccountFor(nft.mint),
+        tokenProgram: addresses.tokenProgram,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([company])
+      .rpc();
+  // The Token-2022 campaign most tests share, created in "Successfully creates NFT campaign"
+  const mainCampaign = campaignAddresses(campaignName, TOKEN_2022_PROGRAM_ID);
+  // One-of-one Token-2022 NFT for mainCampaign's inventory
+  const createMainCampaignNft = () => createCompanyNft({ tokenProgram: TOKEN_2022_PROGRAM_ID });
+
   before(async () => {
     // Airdrop SOL to company
     await provider.connection.confirmTransaction(
@@ -41,6 +169,26 @@ describe("nft-campaign", () => {
       await provider.connection.requestAirdrop(influencer.publicKey, 1e9),
       "confirmed"
     );
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(treasury.publicKey, 1e9),
+      "confirmed"
+    );
+
+    // Initialize the protocol config as the program's upgrade authority
+    const [programDataPda] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    await program.methods
+      .initializeProtocolConfig(treasury.publicKey, protocolFeeBps)
+      .accounts({
+        admin: provider.publicKey,
+        marketplaceAuthority: marketplaceAuthorityPda,
+        program: program.programId,
+        programData: programDataPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
 
     // Create NFT Mint using Token-2022
     const mintSpace = 82; // Size for SPL Token Mint
@@ -95,7 +243,7 @@ describe("nft-campaign", () => {
 
     // Derive campaign PDA
     [campaignPda] = PublicKey.findProgramAddressSync(
-      [Buffer.from("nft_campaign"), Buffer.from(campaignName)],
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from(campaignName)],
       program.programId
     );
 
@@ -109,12 +257,60 @@ describe("nft-campaign", () => {
       [
         Buffer.from("affiliate_link"),
         influencer.publicKey.toBuffer(),
-        Buffer.from(campaignName),
+        campaignPda.toBuffer(),
       ],
       program.programId
     );
+
+    [affiliateVaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_vault"), affiliateLinkPda.toBuffer()],
+      program.programId
+    );
   });
     
+  it("Rejects a campaign with commission above 10000 bps", async () => {
+    const badName = "bad-commission";
+    const [badCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from(badName)],
+      program.programId
+    );
+    const [badEscrowPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_escrow"), badCampaignPda.toBuffer()],
+      program.programId
+    );
+
+    try {
+      await program.methods
+        .createNftCampaign(badName, mintPrice, 10_001, campaignDetails, { keep: {} })
+        .accounts({
+          company: company.publicKey,
+          campaign: badCampaignPda,
+          mintAuthority: PublicKey.findProgramAddressSync(
+            [Buffer.from("mint_authority"), badCampaignPda.toBuffer()],
+            program.programId
+          )[0],
+          nftMint: nftMint.publicKey,
+          projectTokenAccount: companyTokenAccount,
+          nftEscrow: badEscrowPda,
+          escrowPdaNftTokenAccount: getAssociatedTokenAddressSync(
+            nftMint.publicKey,
+            badEscrowPda,
+            true,
+            TOKEN_2022_PROGRAM_ID
+          ),
+          marketplaceAuthority: marketplaceAuthorityPda,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([company])
+        .rpc();
+      assert.fail("Commission above 10000 bps should fail");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidCommissionBps");
+    }
+  });
+
   it("Successfully creates NFT campaign", async () => {
     // Derive escrow's associated token account
     escrowTokenAccount = getAssociatedTokenAddressSync(
@@ -128,16 +324,22 @@ describe("nft-campaign", () => {
       .createNftCampaign(
         campaignName,
         mintPrice,
-        commissionPercentage,
-        campaignDetails
+        commissionBps,
+        campaignDetails,
+        { keep: {} }
       )
       .accounts({
         company: company.publicKey,
         campaign: campaignPda,
+        mintAuthority: PublicKey.findProgramAddressSync(
+          [Buffer.from("mint_authority"), campaignPda.toBuffer()],
+          program.programId
+        )[0],
         nftMint: nftMint.publicKey,
         projectTokenAccount: companyTokenAccount,
         nftEscrow: nftEscrowPda,
         escrowPdaNftTokenAccount: escrowTokenAccount,
+        marketplaceAuthority: marketplaceAuthorityPda,
         tokenProgram: TOKEN_2022_PROGRAM_ID,
         associatedTokenProgram:  ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
@@ -149,7 +351,7 @@ describe("nft-campaign", () => {
     const campaignAccount = await program.account.nftCampaign.fetch(campaignPda);
     assert.equal(campaignAccount.name, campaignName);
     assert.equal(campaignAccount.mintPrice.toString(), mintPrice.toString());
-    assert.equal(campaignAccount.commissionPercentage, commissionPercentage);
+    assert.equal(campaignAccount.commissionBps, commissionBps);
     assert.equal(campaignAccount.campaignDetails, campaignDetails);
     assert.isTrue(campaignAccount.active);
 
@@ -170,13 +372,98 @@ describe("nft-campaign", () => {
       TOKEN_2022_PROGRAM_ID
     );
     assert.equal(companyAccount.amount, BigInt(0));
+    assert.equal(campaignAccount.tokenProgram.toBase58(), TOKEN_2022_PROGRAM_ID.toBase58());
+  });
+
+  it("Creates a campaign for a classic SPL Token collection", async () => {
+    const splCampaignName = "spl-campaign";
+    const splMint = await createMint(
+      provider.connection,
+      company,
+      company.publicKey,
+      null,
+      0,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    const splCompanyAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      company,
+      splMint,
+      company.publicKey,
+      false,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    await mintTo(
+      provider.connection,
+      company,
+      splMint,
+      splCompanyAccount.address,
+      company,
+      1,
+      [],
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+
+    const [splCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from(splCampaignName)],
+      program.programId
+    );
+    const [splEscrowPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_escrow"), splCampaignPda.toBuffer()],
+      program.programId
+    );
+    const splEscrowTokenAccount = getAssociatedTokenAddressSync(
+      splMint,
+      splEscrowPda,
+      true,
+      TOKEN_PROGRAM_ID
+    );
+
+    await program.methods
+      .createNftCampaign(splCampaignName, mintPrice, commissionBps, campaignDetails, { keep: {} })
+      .accounts({
+        company: company.publicKey,
+        campaign: splCampaignPda,
+        mintAuthority: PublicKey.findProgramAddressSync(
+          [Buffer.from("mint_authority"), splCampaignPda.toBuffer()],
+          program.programId
+        )[0],
+        nftMint: splMint,
+        projectTokenAccount: splCompanyAccount.address,
+        nftEscrow: splEscrowPda,
+        escrowPdaNftTokenAccount: splEscrowTokenAccount,
+        marketplaceAuthority: marketplaceAuthorityPda,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([company])
+      .rpc();
+
+    const campaignAccount = await program.account.nftCampaign.fetch(splCampaignPda);
+    assert.equal(campaignAccount.tokenProgram.toBase58(), TOKEN_PROGRAM_ID.toBase58());
+
+    const escrowAccount = await getAccount(
+      provider.connection,
+      splEscrowTokenAccount,
+      "confirmed",
+      TOKEN_PROGRAM_ID
+    );
+    assert.equal(escrowAccount.amount, BigInt(1));
   });
   it("Creates an affiliate link successfully", async () => {
   await program.methods
-    .createAffiliateLink(campaignName)  // Only pass the campaign name parameter
+    .createAffiliateLink()
     .accounts({
       affiliateLink: affiliateLinkPda,
       campaign: campaignPda,
+      affiliateVault: affiliateVaultPda,
+      marketplaceAuthority: marketplaceAuthorityPda,
       influencer: influencer.publicKey,
       systemProgram: SystemProgram.programId,
     })
@@ -213,31 +500,33 @@ describe("nft-campaign", () => {
     "confirmed"
   );
 
-  // Derive marketplace authority PDA
-  const [marketplaceAuthorityPda] = PublicKey.findProgramAddressSync(
-    [Buffer.from("marketplace_authority")],
-    program.programId
-  );
-  const influencerBalanceBefore = await provider.connection.getBalance(influencer.publicKey);
+  const vaultBalanceBefore = await provider.connection.getBalance(affiliateVaultPda);
+  const treasuryBalanceBefore = await provider.connection.getBalance(treasury.publicKey);
   const companyBalanceBefore = await provider.connection.getBalance(company.publicKey);
-  console.log("Influencer Balance Before: ", influencerBalanceBefore);
+  console.log("Vault Balance Before: ", vaultBalanceBefore);
   console.log("Company Balance Before: ", companyBalanceBefore);
 
   // Execute process_affiliate_mint
   await program.methods
-    .processAffiliateMint(campaignName,influencer.publicKey)
+    .processAffiliateMint(null, mintPrice, new anchor.BN(1))
     .accounts({
       campaign: campaignPda,
       affiliateLink: affiliateLinkPda,
-      buyer: buyer.publicKey,
-      owner: company.publicKey,
+      sale: {
+        buyer: buyer.publicKey,
+        payer: buyer.publicKey,
+        owner: company.publicKey,
+        marketplaceAuthority: marketplaceAuthorityPda,
+        treasury: treasury.publicKey,
+      },
+      recipient: buyer.publicKey,
       influencer: influencer.publicKey,
+      affiliateVault: affiliateVaultPda,
       nftMint: nftMint.publicKey,
+      nftMetadata: metadataPda(nftMint.publicKey),
       nftEscrow: nftEscrowPda,
-      buyerTokenAccount: buyerAta,
-      ownerTokenAccount: companyTokenAccount,
+      recipientTokenAccount: buyerAta,
       escrowPdaNftTokenAccount: escrowTokenAccount,
-      marketplaceAuthority: marketplaceAuthorityPda,
       tokenProgram: TOKEN_2022_PROGRAM_ID,
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       systemProgram: SystemProgram.programId,
@@ -245,11 +534,14 @@ describe("nft-campaign", () => {
     .signers([buyer])
     .rpc();
 
-  // Verify influencer received commission
-  const influencerBalanceAfter = await provider.connection.getBalance(influencer.publicKey);
-  console.log("Influencer Balance After: ", influencerBalanceAfter);
-  assert.isAbove(influencerBalanceAfter,influencerBalanceBefore , "Influencer should receive commission");
-  //assert.isAbove(influencerBalanceAfter, 1e9, "Influencer should receive commission");
+  // Verify the commission landed in the affiliate vault
+  const vaultBalanceAfter = await provider.connection.getBalance(affiliateVaultPda);
+  console.log("Vault Balance After: ", vaultBalanceAfter);
+  assert.equal(vaultBalanceAfter - vaultBalanceBefore, expectedCommission, "Vault should hold the commission");
+
+  // Verify the treasury received the protocol fee
+  const treasuryBalanceAfter = await provider.connection.getBalance(treasury.publicKey);
+  assert.equal(treasuryBalanceAfter - treasuryBalanceBefore, protocolFee, "Treasury should receive the protocol fee");
 
   // Verify company received the project amount
   const companyBalanceAfter = await provider.connection.getBalance(company.publicKey);
@@ -276,13 +568,2500 @@ describe("nft-campaign", () => {
   // Verify affiliate link state updated
   const affiliateLinkAccount = await program.account.affiliateLink.fetch(affiliateLinkPda);
   assert.equal(affiliateLinkAccount.mintsCount.toString(), "1");
-  assert.equal(affiliateLinkAccount.earnings.toString(), (mintPrice.toNumber() * commissionPercentage / 100).toString());
+  assert.equal(affiliateLinkAccount.earnings.toString(), expectedCommission.toString());
+  assert.equal(affiliateLinkAccount.unclaimedEarnings.toString(), affiliateLinkAccount.earnings.toString());
 
   // Verify campaign state updated
   const campaignAccount = await program.account.nftCampaign.fetch(campaignPda);
   assert.equal(campaignAccount.totalMints.toString(), "1");
+  assert.equal(campaignAccount.availableSupply.toString(), "0");
 });
 
+  it("Lets the influencer claim earnings from the vault", async () => {
+    const influencerBalanceBefore = await provider.connection.getBalance(influencer.publicKey);
+
+    await program.methods
+      .claimEarnings()
+      .accounts({
+        influencer: influencer.publicKey,
+        affiliateLink: affiliateLinkPda,
+        affiliateVault: affiliateVaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([influencer])
+      .rpc();
+
+    const influencerBalanceAfter = await provider.connection.getBalance(influencer.publicKey);
+    assert.isAbove(influencerBalanceAfter, influencerBalanceBefore, "Influencer should receive commission");
+
+    const affiliateLinkAccount = await program.account.affiliateLink.fetch(affiliateLinkPda);
+    assert.equal(affiliateLinkAccount.claimedEarnings.toString(), expectedCommission.toString());
+    assert.equal(affiliateLinkAccount.unclaimedEarnings.toString(), "0");
+
+    // Nothing left to claim
+    try {
+      await program.methods
+        .claimEarnings()
+        .accounts({
+          influencer: influencer.publicKey,
+          affiliateLink: affiliateLinkPda,
+          affiliateVault: affiliateVaultPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([influencer])
+        .rpc();
+      assert.fail("Claiming twice should fail");
+    } catch (err) {
+      assert.include(err.toString(), "NothingToClaim");
+    }
+  });
+
+  it("Rejects a sale once the escrow is empty", async () => {
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+    const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+
+    try {
+      await program.methods
+        .processAffiliateMint(null, mintPrice, new anchor.BN(1))
+        .accounts({
+          campaign: campaignPda,
+          affiliateLink: affiliateLinkPda,
+          sale: {
+            buyer: buyer.publicKey,
+            payer: buyer.publicKey,
+            owner: company.publicKey,
+            marketplaceAuthority: marketplaceAuthorityPda,
+            treasury: treasury.publicKey,
+          },
+          recipient: buyer.publicKey,
+          influencer: influencer.publicKey,
+          affiliateVault: affiliateVaultPda,
+          nftMint: nftMint.publicKey,
+          nftMetadata: metadataPda(nftMint.publicKey),
+          nftEscrow: nftEscrowPda,
+          recipientTokenAccount: getAssociatedTokenAddressSync(
+            nftMint.publicKey,
+            buyer.publicKey,
+            false,
+            TOKEN_2022_PROGRAM_ID
+          ),
+          escrowPdaNftTokenAccount: escrowTokenAccount,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+      assert.fail("Buying from an empty escrow should fail");
+    } catch (err) {
+      assert.include(err.toString(), "SoldOut");
+    }
+
+    const buyerBalanceAfter = await provider.connection.getBalance(buyer.publicKey);
+    assert.equal(buyerBalanceAfter, buyerBalanceBefore, "Buyer should not be charged");
+  });
+
+  it("Deposits more inventory into the campaign escrow", async () => {
+    // Any one-of-one can join the escrow, not only the campaign's first NFT
+    restockedNft = await createMainCampaignNft();
+    await depositCompanyNft(mainCampaign, restockedNft);
+
+    const campaignAccount = await program.account.nftCampaign.fetch(campaignPda);
+    assert.equal(campaignAccount.availableSupply.toString(), "1");
+
+    const escrowAccount = await getAccount(
+      provider.connection,
+      mainCampaign.escrowAccountFor(restockedNft.mint),
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    assert.equal(escrowAccount.amount, BigInt(1));
+  });
+
+  it("Withdraws unsold inventory back to the company", async () => {
+    await program.methods
+      .withdrawInventory(new anchor.BN(1))
+      .accounts({
+        company: company.publicKey,
+        campaign: campaignPda,
+        nftMint: restockedNft.mint,
+        nftEscrow: nftEscrowPda,
+        escrowPdaNftTokenAccount: mainCampaign.escrowAccountFor(restockedNft.mint),
+        projectTokenAccount: restockedNft.account,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([company])
+      .rpc();
+
+    const campaignAccount = await program.account.nftCampaign.fetch(campaignPda);
+    assert.equal(campaignAccount.availableSupply.toString(), "0");
+
+    const companyAccount = await getAccount(
+      provider.connection,
+      restockedNft.account,
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    assert.equal(companyAccount.amount, BigInt(1));
+    // The emptied escrow token account is closed
+    assert.isNull(await provider.connection.getAccountInfo(mainCampaign.escrowAccountFor(restockedNft.mint)));
+  });
+
+  it("Pauses and resumes the campaign", async () => {
+    await program.methods
+      .pauseCampaign()
+      .accounts({
+        company: company.publicKey,
+        campaign: campaignPda,
+      })
+      .signers([company])
+      .rpc();
+
+    let campaignAccount = await program.account.nftCampaign.fetch(campaignPda);
+    assert.isFalse(campaignAccount.active);
+
+    // Pausing twice is rejected
+    try {
+      await program.methods
+        .pauseCampaign()
+        .accounts({
+          company: company.publicKey,
+          campaign: campaignPda,
+        })
+        .signers([company])
+        .rpc();
+      assert.fail("Pausing a paused campaign should fail");
+    } catch (err) {
+      assert.include(err.toString(), "CampaignAlreadyPaused");
+    }
+
+    await program.methods
+      .resumeCampaign()
+      .accounts({
+        company: company.publicKey,
+        campaign: campaignPda,
+      })
+      .signers([company])
+      .rpc();
+
+    campaignAccount = await program.account.nftCampaign.fetch(campaignPda);
+    assert.isTrue(campaignAccount.active);
+  });
+
+  it("Updates campaign terms", async () => {
+    const newDetails = campaignDetails + " - now with a much longer description for affiliates";
+    await program.methods
+      .updateCampaign(new anchor.BN(2_000_000), 1_500, newDetails)
+      .accounts({
+        company: company.publicKey,
+        campaign: campaignPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([company])
+      .rpc();
+
+    const campaignAccount = await program.account.nftCampaign.fetch(campaignPda);
+    assert.equal(campaignAccount.mintPrice.toString(), "2000000");
+    assert.equal(campaignAccount.commissionBps, 1_500);
+    assert.equal(campaignAccount.campaignDetails, newDetails);
+
+    // Commission above 10000 bps is rejected
+    try {
+      await program.methods
+        .updateCampaign(null, 10_001, null)
+        .accounts({
+          company: company.publicKey,
+          campaign: campaignPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([company])
+        .rpc();
+      assert.fail("Commission above 10000 bps should fail");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidCommissionBps");
+    }
+  });
+
+  it("Blocks sales while the protocol is paused", async () => {
+    // A campaign with one NFT sold, so there are earnings to claim, and one still escrowed
+    const nfts = [await createCompanyNft(), await createCompanyNft()];
+    const pauseCampaign = await createEscrowCampaign("pause-campaign", nfts[0]);
+    await depositCompanyNft(pauseCampaign, nfts[1]);
+    const linkFor = (wallet: PublicKey) => {
+      const [link] = PublicKey.findProgramAddressSync(
+        [Buffer.from("affiliate_link"), wallet.toBuffer(), pauseCampaign.campaign.toBuffer()],
+        program.programId
+      );
+      const [vault] = PublicKey.findProgramAddressSync([Buffer.from("affiliate_vault"), link.toBuffer()], program.programId);
+      return { link, vault };
+    };
+    const createLink = (wallet: Keypair) =>
+      program.methods
+        .createAffiliateLink()
+        .accounts({
+          affiliateLink: linkFor(wallet.publicKey).link,
+          campaign: pauseCampaign.campaign,
+          affiliateVault: linkFor(wallet.publicKey).vault,
+          marketplaceAuthority: marketplaceAuthorityPda,
+          influencer: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([wallet])
+        .rpc();
+    await createLink(influencer);
+    const { link: pauseLinkPda, vault: pauseVaultPda } = linkFor(influencer.publicKey);
+
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+    const purchase = (nft: { mint: PublicKey }) =>
+      program.methods
+        .processAffiliateMint(null, mintPrice, new anchor.BN(1))
+        .accounts({
+          campaign: pauseCampaign.campaign,
+          affiliateLink: pauseLinkPda,
+          sale: {
+            buyer: buyer.publicKey,
+            payer: buyer.publicKey,
+            owner: company.publicKey,
+            marketplaceAuthority: marketplaceAuthorityPda,
+            treasury: treasury.publicKey,
+          },
+          recipient: buyer.publicKey,
+          influencer: influencer.publicKey,
+          affiliateVault: pauseVaultPda,
+          nftMint: nft.mint,
+          nftMetadata: metadataPda(nft.mint),
+          nftEscrow: pauseCampaign.escrow,
+          recipientTokenAccount: getAssociatedTokenAddressSync(nft.mint, buyer.publicKey, false, TOKEN_PROGRAM_ID),
+          escrowPdaNftTokenAccount: pauseCampaign.escrowAccountFor(nft.mint),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+    await purchase(nfts[0]);
+
+    await program.methods
+      .setProtocolPaused(true)
+      .accounts({
+        admin: provider.publicKey,
+        marketplaceAuthority: marketplaceAuthorityPda,
+      })
+      .rpc();
+    try {
+      // Sales, new campaigns and new affiliate links all stop
+      try {
+        await purchase(nfts[1]);
+        assert.fail("Sales should fail while the protocol is paused");
+      } catch (err) {
+        assert.include(err.toString(), "ProtocolPaused");
+      }
+      try {
+        await createEscrowCampaign("paused-new-campaign", await createCompanyNft());
+        assert.fail("Campaigns should not be created while the protocol is paused");
+      } catch (err) {
+        assert.include(err.toString(), "ProtocolPaused");
+      }
+      const newInfluencer = Keypair.generate();
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(newInfluencer.publicKey, 1e9),
+        "confirmed"
+      );
+      try {
+        await createLink(newInfluencer);
+        assert.fail("Affiliate links should not be created while the protocol is paused");
+      } catch (err) {
+        assert.include(err.toString(), "ProtocolPaused");
+      }
+
+      // Nobody's funds or NFTs are locked in: the company withdraws and the influencer claims
+      await program.methods
+        .withdrawInventory(new anchor.BN(1))
+        .accounts({
+          company: company.publicKey,
+          campaign: pauseCampaign.campaign,
+          nftMint: nfts[1].mint,
+          nftEscrow: pauseCampaign.escrow,
+          escrowPdaNftTokenAccount: pauseCampaign.escrowAccountFor(nfts[1].mint),
+          projectTokenAccount: nfts[1].account,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([company])
+        .rpc();
+      const companyAccount = await getAccount(provider.connection, nfts[1].account, "confirmed", TOKEN_PROGRAM_ID);
+      assert.equal(companyAccount.amount, BigInt(1));
+
+      await program.methods
+        .claimEarnings()
+        .accounts({
+          influencer: influencer.publicKey,
+          affiliateLink: pauseLinkPda,
+          affiliateVault: pauseVaultPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([influencer])
+        .rpc();
+      const link = await program.account.affiliateLink.fetch(pauseLinkPda);
+      assert.equal(link.unclaimedEarnings.toNumber(), 0);
+      assert.equal(link.claimedEarnings.toNumber(), expectedCommission);
+    } finally {
+      await program.methods
+        .setProtocolPaused(false)
+        .accounts({
+          admin: provider.publicKey,
+          marketplaceAuthority: marketplaceAuthorityPda,
+        })
+        .rpc();
+    }
+  });
+
+  it("Lets only the admin update the protocol config", async () => {
+    try {
+      await program.methods
+        .updateProtocolConfig(null, null, 0)
+        .accounts({
+          admin: company.publicKey,
+          marketplaceAuthority: marketplaceAuthorityPda,
+        })
+        .signers([company])
+        .rpc();
+      assert.fail("Only the admin can update the protocol config");
+    } catch (err) {
+      assert.include(err.toString(), "Unauthorized");
+    }
+
+    await program.methods
+      .updateProtocolConfig(null, null, protocolFeeBps)
+      .accounts({
+        admin: provider.publicKey,
+        marketplaceAuthority: marketplaceAuthorityPda,
+      })
+      .rpc();
+
+    const config = await program.account.protocolConfig.fetch(marketplaceAuthorityPda);
+    assert.equal(config.protocolFeeBps, protocolFeeBps);
+    assert.equal(config.treasury.toBase58(), treasury.publicKey.toBase58());
+  });
+
+  it("Rejects lifecycle changes from other wallets", async () => {
+    try {
+      await program.methods
+        .pauseCampaign()
+        .accounts({
+          company: influencer.publicKey,
+          campaign: campaignPda,
+        })
+        .signers([influencer])
+        .rpc();
+      assert.fail("Only the company can pause its campaign");
+    } catch (err) {
+      assert.include(err.toString(), "Unauthorized");
+    }
+  });
+
+  it("Mints a new NFT for each sale of a fresh-mint campaign", async () => {
+    const freshCampaignName = "fresh-campaign";
+    const [freshCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from(freshCampaignName)],
+      program.programId
+    );
+    const [freshLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), freshCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [freshVaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_vault"), freshLinkPda.toBuffer()],
+      program.programId
+    );
+    const [mintAuthorityPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint_authority"), freshCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [freshMintPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fresh_mint"), freshCampaignPda.toBuffer(), new anchor.BN(0).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    await program.methods
+      .createFreshMintCampaign(
+        freshCampaignName,
+        mintPrice,
+        commissionBps,
+        campaignDetails,
+        "https://example.com/fresh/",
+        new anchor.BN(10)
+      )
+      .accounts({
+        company: company.publicKey,
+        campaign: freshCampaignPda,
+        marketplaceAuthority: marketplaceAuthorityPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([company])
+      .rpc();
+
+    await program.methods
+      .createAffiliateLink()
+      .accounts({
+        affiliateLink: freshLinkPda,
+        campaign: freshCampaignPda,
+        affiliateVault: freshVaultPda,
+        marketplaceAuthority: marketplaceAuthorityPda,
+        influencer: influencer.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([influencer])
+      .rpc();
+
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+    const buyerAta = getAssociatedTokenAddressSync(
+      freshMintPda,
+      buyer.publicKey,
+      false,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    await program.methods
+      .processAffiliateFreshMint(null, mintPrice, new anchor.BN(1))
+      .accounts({
+        campaign: freshCampaignPda,
+        affiliateLink: freshLinkPda,
+        sale: {
+          buyer: buyer.publicKey,
+          payer: buyer.publicKey,
+          owner: company.publicKey,
+          marketplaceAuthority: marketplaceAuthorityPda,
+          treasury: treasury.publicKey,
+        },
+        recipient: buyer.publicKey,
+        influencer: influencer.publicKey,
+        affiliateVault: freshVaultPda,
+        mintAuthority: mintAuthorityPda,
+        nftMint: freshMintPda,
+        groupMint: null,
+        recipientTokenAccount: buyerAta,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const buyerAccount = await getAccount(
+      provider.connection,
+      buyerAta,
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    assert.equal(buyerAccount.amount, BigInt(1), "Buyer should receive the freshly minted NFT");
+
+    const campaignAccount = await program.account.nftCampaign.fetch(freshCampaignPda);
+    assert.equal(campaignAccount.freshMintCount.toString(), "1");
+    assert.equal(campaignAccount.availableSupply.toString(), "9");
+
+    const linkAccount = await program.account.affiliateLink.fetch(freshLinkPda);
+    assert.equal(linkAccount.earnings.toString(), expectedCommission.toString());
+  });
+
+  it("Registers fresh mints as members of the campaign group", async () => {
+    const [freshCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from("fresh-campaign")],
+      program.programId
+    );
+    const [freshLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), freshCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [freshVaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_vault"), freshLinkPda.toBuffer()],
+      program.programId
+    );
+    const [mintAuthorityPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint_authority"), freshCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [groupMintPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("campaign_group"), freshCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [secondMintPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fresh_mint"), freshCampaignPda.toBuffer(), new anchor.BN(1).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    // Lamports sent to the predictable group address must not block its creation
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        SystemProgram.transfer({
+          fromPubkey: provider.publicKey,
+          toPubkey: groupMintPda,
+          lamports: 1,
+        })
+      )
+    );
+    await program.methods
+      .createCampaignGroup(100)
+      .accounts({
+        company: company.publicKey,
+        campaign: freshCampaignPda,
+        mintAuthority: mintAuthorityPda,
+        groupMint: groupMintPda,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([company])
+      .rpc();
+
+    const campaignAccount = await program.account.nftCampaign.fetch(freshCampaignPda);
+    assert.equal(campaignAccount.groupMint.toBase58(), groupMintPda.toBase58());
+
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+    await program.methods
+      .processAffiliateFreshMint(null, mintPrice, new anchor.BN(1))
+      .accounts({
+        campaign: freshCampaignPda,
+        affiliateLink: freshLinkPda,
+        sale: {
+          buyer: buyer.publicKey,
+          payer: buyer.publicKey,
+          owner: company.publicKey,
+          marketplaceAuthority: marketplaceAuthorityPda,
+          treasury: treasury.publicKey,
+        },
+        recipient: buyer.publicKey,
+        influencer: influencer.publicKey,
+        affiliateVault: freshVaultPda,
+        mintAuthority: mintAuthorityPda,
+        nftMint: secondMintPda,
+        groupMint: groupMintPda,
+        recipientTokenAccount: getAssociatedTokenAddressSync(
+          secondMintPda,
+          buyer.publicKey,
+          false,
+          TOKEN_2022_PROGRAM_ID
+        ),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const mint = await getMint(provider.connection, secondMintPda, "confirmed", TOKEN_2022_PROGRAM_ID);
+    const member = getTokenGroupMemberState(mint);
+    assert.equal(member.group.toBase58(), groupMintPda.toBase58());
+    assert.equal(member.memberNumber, 1);
+  });
+
+  it("Takes payment in a stablecoin when the campaign has a payment mint", async () => {
+    const usdcCampaignName = "usdc-campaign";
+    const usdcPrice = new anchor.BN(25_000_000); // 25 USDC with 6 decimals
+    const usdcMint = await createMint(provider.connection, company, company.publicKey, null, 6);
+    const [usdcCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from(usdcCampaignName)],
+      program.programId
+    );
+    const [usdcLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), usdcCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [usdcVaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_vault"), usdcLinkPda.toBuffer()],
+      program.programId
+    );
+    const [mintAuthorityPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint_authority"), usdcCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [freshMintPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fresh_mint"), usdcCampaignPda.toBuffer(), new anchor.BN(0).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const vaultUsdcAccount = getAssociatedTokenAddressSync(usdcMint, usdcVaultPda, true);
+
+    await program.methods
+      .createFreshMintCampaign(
+        usdcCampaignName,
+        usdcPrice,
+        commissionBps,
+        campaignDetails,
+        "https://example.com/usdc/",
+        new anchor.BN(10)
+      )
+      .accounts({
+        company: company.publicKey,
+        campaign: usdcCampaignPda,
+        marketplaceAuthority: marketplaceAuthorityPda,
+        paymentMint: usdcMint,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([company])
+      .rpc();
+
+    await program.methods
+      .createAffiliateLink()
+      .accounts({
+        affiliateLink: usdcLinkPda,
+        campaign: usdcCampaignPda,
+        affiliateVault: usdcVaultPda,
+        marketplaceAuthority: marketplaceAuthorityPda,
+        influencer: influencer.publicKey,
+        paymentMint: usdcMint,
+        vaultPaymentAccount: vaultUsdcAccount,
+        paymentTokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([influencer])
+      .rpc();
+
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+    const buyerUsdc = await getOrCreateAssociatedTokenAccount(provider.connection, company, usdcMint, buyer.publicKey);
+    const treasuryUsdc = await getOrCreateAssociatedTokenAccount(provider.connection, company, usdcMint, treasury.publicKey);
+    const companyUsdc = await getOrCreateAssociatedTokenAccount(provider.connection, company, usdcMint, company.publicKey);
+    await mintTo(provider.connection, company, usdcMint, buyerUsdc.address, company, usdcPrice.toNumber());
+
+    await program.methods
+      .processAffiliateFreshMint(null, usdcPrice, new anchor.BN(1))
+      .accounts({
+        campaign: usdcCampaignPda,
+        affiliateLink: usdcLinkPda,
+        sale: {
+          buyer: buyer.publicKey,
+          payer: buyer.publicKey,
+          owner: company.publicKey,
+          marketplaceAuthority: marketplaceAuthorityPda,
+          treasury: treasury.publicKey,
+          paymentMint: usdcMint,
+          buyerPaymentAccount: buyerUsdc.address,
+          treasuryPaymentAccount: treasuryUsdc.address,
+          companyPaymentAccount: companyUsdc.address,
+          paymentTokenProgram: TOKEN_PROGRAM_ID,
+        },
+        recipient: buyer.publicKey,
+        influencer: influencer.publicKey,
+        affiliateVault: usdcVaultPda,
+        mintAuthority: mintAuthorityPda,
+        nftMint: freshMintPda,
+        groupMint: null,
+        recipientTokenAccount: getAssociatedTokenAddressSync(
+          freshMintPda,
+          buyer.publicKey,
+          false,
+          TOKEN_2022_PROGRAM_ID
+        ),
+        vaultPaymentAccount: vaultUsdcAccount,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const usdcFee = Math.floor(usdcPrice.toNumber() * protocolFeeBps / 10_000);
+    const usdcCommission = Math.floor((usdcPrice.toNumber() - usdcFee) * commissionBps / 10_000);
+    assert.equal((await getAccount(provider.connection, treasuryUsdc.address)).amount, BigInt(usdcFee));
+    assert.equal((await getAccount(provider.connection, vaultUsdcAccount)).amount, BigInt(usdcCommission));
+    assert.equal(
+      (await getAccount(provider.connection, companyUsdc.address)).amount,
+      BigInt(usdcPrice.toNumber() - usdcFee - usdcCommission)
+    );
+
+    const influencerUsdc = await getOrCreateAssociatedTokenAccount(provider.connection, company, usdcMint, influencer.publicKey);
+    await program.methods
+      .claimEarnings()
+      .accounts({
+        influencer: influencer.publicKey,
+        affiliateLink: usdcLinkPda,
+        affiliateVault: usdcVaultPda,
+        paymentMint: usdcMint,
+        vaultPaymentAccount: vaultUsdcAccount,
+        influencerPaymentAccount: influencerUsdc.address,
+        paymentTokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([influencer])
+      .rpc();
+
+    assert.equal((await getAccount(provider.connection, influencerUsdc.address)).amount, BigInt(usdcCommission));
+    const linkAccount = await program.account.affiliateLink.fetch(usdcLinkPda);
+    assert.equal(linkAccount.unclaimedEarnings.toString(), "0");
+  });
+
+  it("Sells in any currency from the campaign price table", async () => {
+    const [usdcCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from("usdc-campaign")],
+      program.programId
+    );
+    const [usdcLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), usdcCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [usdcVaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_vault"), usdcLinkPda.toBuffer()],
+      program.programId
+    );
+    const [priceTablePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("price_table"), usdcCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [solEarningsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_earnings"), usdcLinkPda.toBuffer(), PublicKey.default.toBuffer()],
+      program.programId
+    );
+    const [mintAuthorityPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint_authority"), usdcCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [freshMintPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fresh_mint"), usdcCampaignPda.toBuffer(), new anchor.BN(1).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    await program.methods
+      .setPriceTable([{ mint: PublicKey.default, price: mintPrice }])
+      .accounts({
+        company: company.publicKey,
+        campaign: usdcCampaignPda,
+        priceTable: priceTablePda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([company])
+      .rpc();
+
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+    const accounts = {
+      campaign: usdcCampaignPda,
+      affiliateLink: usdcLinkPda,
+      sale: {
+        buyer: buyer.publicKey,
+        payer: buyer.publicKey,
+        owner: company.publicKey,
+        marketplaceAuthority: marketplaceAuthorityPda,
+        treasury: treasury.publicKey,
+        priceTable: priceTablePda,
+      },
+      recipient: buyer.publicKey,
+      influencer: influencer.publicKey,
+      affiliateVault: usdcVaultPda,
+      mintAuthority: mintAuthorityPda,
+      nftMint: freshMintPda,
+      groupMint: null,
+      recipientTokenAccount: getAssociatedTokenAddressSync(
+        freshMintPda,
+        buyer.publicKey,
+        false,
+        TOKEN_2022_PROGRAM_ID
+      ),
+      affiliateEarnings: solEarningsPda,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    };
+
+    // Currencies missing from the table are refused
+    const unlistedCurrency = Keypair.generate().publicKey;
+    const [unlistedEarningsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_earnings"), usdcLinkPda.toBuffer(), unlistedCurrency.toBuffer()],
+      program.programId
+    );
+    try {
+      await program.methods
+        .processAffiliateFreshMint(unlistedCurrency, mintPrice, new anchor.BN(1))
+        .accounts({ ...accounts, affiliateEarnings: unlistedEarningsPda })
+        .signers([buyer])
+        .rpc();
+      assert.fail("Unlisted currencies should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "UnsupportedCurrency");
+    }
+
+    const vaultBalanceBefore = await provider.connection.getBalance(usdcVaultPda);
+    await program.methods
+      .processAffiliateFreshMint(PublicKey.default, mintPrice, new anchor.BN(1))
+      .accounts(accounts)
+      .signers([buyer])
+      .rpc();
+
+    const vaultBalanceAfter = await provider.connection.getBalance(usdcVaultPda);
+    assert.equal(vaultBalanceAfter - vaultBalanceBefore, expectedCommission);
+
+    // SOL commissions are booked apart from the campaign's USDC earnings
+    const solEarnings = await program.account.affiliateEarnings.fetch(solEarningsPda);
+    assert.equal(solEarnings.currency.toBase58(), PublicKey.default.toBase58());
+    assert.equal(solEarnings.unclaimedEarnings.toString(), expectedCommission.toString());
+    const linkAccount = await program.account.affiliateLink.fetch(usdcLinkPda);
+    assert.equal(linkAccount.unclaimedEarnings.toString(), "0");
+
+    await program.methods
+      .claimEarnings()
+      .accounts({
+        influencer: influencer.publicKey,
+        affiliateLink: usdcLinkPda,
+        affiliateVault: usdcVaultPda,
+        affiliateEarnings: solEarningsPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([influencer])
+      .rpc();
+
+    const claimed = await program.account.affiliateEarnings.fetch(solEarningsPda);
+    assert.equal(claimed.unclaimedEarnings.toString(), "0");
+    assert.equal(claimed.claimedEarnings.toString(), expectedCommission.toString());
+  });
+
+  it("Rejects a sale above the buyer's max total price", async () => {
+    const [freshCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from("fresh-campaign")],
+      program.programId
+    );
+    const [freshLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), freshCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [nextMintPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fresh_mint"), freshCampaignPda.toBuffer(), new anchor.BN(2).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+    const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+
+    try {
+      await program.methods
+        .processAffiliateFreshMint(null, mintPrice.subn(1), new anchor.BN(1))
+        .accounts({
+          campaign: freshCampaignPda,
+          affiliateLink: freshLinkPda,
+          sale: {
+            buyer: buyer.publicKey,
+            payer: buyer.publicKey,
+            owner: company.publicKey,
+            marketplaceAuthority: marketplaceAuthorityPda,
+            treasury: treasury.publicKey,
+          },
+          recipient: buyer.publicKey,
+          influencer: influencer.publicKey,
+          affiliateVault: PublicKey.findProgramAddressSync(
+            [Buffer.from("affiliate_vault"), freshLinkPda.toBuffer()],
+            program.programId
+          )[0],
+          mintAuthority: PublicKey.findProgramAddressSync(
+            [Buffer.from("mint_authority"), freshCampaignPda.toBuffer()],
+            program.programId
+          )[0],
+          nftMint: nextMintPda,
+          groupMint: PublicKey.findProgramAddressSync(
+            [Buffer.from("campaign_group"), freshCampaignPda.toBuffer()],
+            program.programId
+          )[0],
+          recipientTokenAccount: getAssociatedTokenAddressSync(
+            nextMintPda,
+            buyer.publicKey,
+            false,
+            TOKEN_2022_PROGRAM_ID
+          ),
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+      assert.fail("Sales above max_total_price should fail");
+    } catch (err) {
+      assert.include(err.toString(), "MaxTotalPriceExceeded");
+    }
+
+    const buyerBalanceAfter = await provider.connection.getBalance(buyer.publicKey);
+    assert.equal(buyerBalanceAfter, buyerBalanceBefore, "Buyer should not be charged");
+  });
+
+  it("Converts a USD price to lamports with the campaign price feed", async () => {
+    // Local fixture from Anchor.toml: SOL at $150.00, published long before the test run
+    const priceFeed = new PublicKey("6Lxrw71CTYtWaXR4noDbPSj9wKhkuYC84UVcNtQN5q4p");
+    const usdCents = new anchor.BN(1_500);
+    const expectedLamports = 100_000_000; // $15.00 at $150.00 per SOL
+    const [freshCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from("fresh-campaign")],
+      program.programId
+    );
+    const [freshLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), freshCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [freshVaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_vault"), freshLinkPda.toBuffer()],
+      program.programId
+    );
+    const [mintAuthorityPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint_authority"), freshCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [groupMintPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("campaign_group"), freshCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [thirdMintPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fresh_mint"), freshCampaignPda.toBuffer(), new anchor.BN(2).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    const setPriceFeed = (maxPriceAge: anchor.BN) =>
+      program.methods
+        .setPriceFeed(usdCents, maxPriceAge)
+        .accounts({
+          company: company.publicKey,
+          campaign: freshCampaignPda,
+          priceFeed,
+        })
+        .signers([company])
+        .rpc();
+
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+    const purchase = () =>
+      program.methods
+        .processAffiliateFreshMint(null, new anchor.BN(expectedLamports), new anchor.BN(1))
+        .accounts({
+          campaign: freshCampaignPda,
+          affiliateLink: freshLinkPda,
+          sale: {
+            buyer: buyer.publicKey,
+            payer: buyer.publicKey,
+            owner: company.publicKey,
+            marketplaceAuthority: marketplaceAuthorityPda,
+            treasury: treasury.publicKey,
+            priceFeed,
+          },
+          recipient: buyer.publicKey,
+          influencer: influencer.publicKey,
+          affiliateVault: freshVaultPda,
+          mintAuthority: mintAuthorityPda,
+          nftMint: thirdMintPda,
+          groupMint: groupMintPda,
+          recipientTokenAccount: getAssociatedTokenAddressSync(
+            thirdMintPda,
+            buyer.publicKey,
+            false,
+            TOKEN_2022_PROGRAM_ID
+          ),
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+
+    // The fixture is older than a minute, so a tight staleness bound refuses it
+    await setPriceFeed(new anchor.BN(60));
+    try {
+      await purchase();
+      assert.fail("Stale prices should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "StalePriceFeed");
+    }
+
+    await setPriceFeed(new anchor.BN(2).pow(new anchor.BN(62)));
+    const vaultBalanceBefore = await provider.connection.getBalance(freshVaultPda);
+    await purchase();
+    const vaultBalanceAfter = await provider.connection.getBalance(freshVaultPda);
+
+    const fee = Math.floor(expectedLamports * protocolFeeBps / 10_000);
+    const commission = Math.floor((expectedLamports - fee) * commissionBps / 10_000);
+    assert.equal(vaultBalanceAfter - vaultBalanceBefore, commission);
+
+    const campaignAccount = await program.account.nftCampaign.fetch(freshCampaignPda);
+    assert.equal(campaignAccount.priceFeed.toBase58(), priceFeed.toBase58());
+    assert.equal(campaignAccount.mintPrice.toString(), usdCents.toString());
+  });
+
+  it("Mints several NFTs in one purchase and scales the commission", async () => {
+    // Still priced through the feed fixture: $15.00 each at $150.00 per SOL
+    const priceFeed = new PublicKey("6Lxrw71CTYtWaXR4noDbPSj9wKhkuYC84UVcNtQN5q4p");
+    const unitPrice = 100_000_000;
+    const quantity = 2;
+    const [freshCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from("fresh-campaign")],
+      program.programId
+    );
+    const [freshLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), freshCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [freshVaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_vault"), freshLinkPda.toBuffer()],
+      program.programId
+    );
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+    const [firstMintPda, secondMintPda] = [3, 4].map(
+      (index) =>
+        PublicKey.findProgramAddressSync(
+          [Buffer.from("fresh_mint"), freshCampaignPda.toBuffer(), new anchor.BN(index).toArrayLike(Buffer, "le", 8)],
+          program.programId
+        )[0]
+    );
+    const buyerAta = (mint: PublicKey) =>
+      getAssociatedTokenAddressSync(mint, buyer.publicKey, false, TOKEN_2022_PROGRAM_ID);
+
+    const campaignBefore = await program.account.nftCampaign.fetch(freshCampaignPda);
+    const linkBefore = await program.account.affiliateLink.fetch(freshLinkPda);
+    const vaultBalanceBefore = await provider.connection.getBalance(freshVaultPda);
+
+    await program.methods
+      .processAffiliateFreshMint(null, new anchor.BN(unitPrice * quantity), new anchor.BN(quantity))
+      .accounts({
+        campaign: freshCampaignPda,
+        affiliateLink: freshLinkPda,
+        sale: {
+          buyer: buyer.publicKey,
+          payer: buyer.publicKey,
+          owner: company.publicKey,
+          marketplaceAuthority: marketplaceAuthorityPda,
+          treasury: treasury.publicKey,
+          priceFeed,
+        },
+        recipient: buyer.publicKey,
+        influencer: influencer.publicKey,
+        affiliateVault: freshVaultPda,
+        mintAuthority: PublicKey.findProgramAddressSync(
+          [Buffer.from("mint_authority"), freshCampaignPda.toBuffer()],
+          program.programId
+        )[0],
+        nftMint: firstMintPda,
+        groupMint: PublicKey.findProgramAddressSync(
+          [Buffer.from("campaign_group"), freshCampaignPda.toBuffer()],
+          program.programId
+        )[0],
+        recipientTokenAccount: buyerAta(firstMintPda),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts([
+        { pubkey: secondMintPda, isWritable: true, isSigner: false },
+        { pubkey: buyerAta(secondMintPda), isWritable: true, isSigner: false },
+      ])
+      .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 800_000 })])
+      .signers([buyer])
+      .rpc();
+
+    for (const mint of [firstMintPda, secondMintPda]) {
+      const account = await getAccount(provider.connection, buyerAta(mint), "confirmed", TOKEN_2022_PROGRAM_ID);
+      assert.equal(account.amount.toString(), "1");
+    }
+
+    const fee = Math.floor(unitPrice * protocolFeeBps / 10_000);
+    const commission = Math.floor((unitPrice - fee) * commissionBps / 10_000);
+    const vaultBalanceAfter = await provider.connection.getBalance(freshVaultPda);
+    assert.equal(vaultBalanceAfter - vaultBalanceBefore, commission * quantity);
+
+    const campaignAfter = await program.account.nftCampaign.fetch(freshCampaignPda);
+    assert.equal(campaignAfter.freshMintCount.toNumber(), campaignBefore.freshMintCount.toNumber() + quantity);
+    assert.equal(campaignAfter.totalMints.toNumber(), campaignBefore.totalMints.toNumber() + quantity);
+    assert.equal(campaignAfter.availableSupply.toNumber(), campaignBefore.availableSupply.toNumber() - quantity);
+    const linkAfter = await program.account.affiliateLink.fetch(freshLinkPda);
+    assert.equal(linkAfter.mintsCount.toNumber(), linkBefore.mintsCount.toNumber() + quantity);
+    assert.equal(linkAfter.unclaimedEarnings.toNumber(), linkBefore.unclaimedEarnings.toNumber() + commission * quantity);
+  });
+
+  it("Delivers a gifted NFT to a recipient other than the buyer", async () => {
+    const priceFeed = new PublicKey("6Lxrw71CTYtWaXR4noDbPSj9wKhkuYC84UVcNtQN5q4p");
+    const [freshCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from("fresh-campaign")],
+      program.programId
+    );
+    const [freshLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), freshCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [giftMintPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fresh_mint"), freshCampaignPda.toBuffer(), new anchor.BN(5).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const buyer = Keypair.generate();
+    const recipient = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+    const recipientTokenAccount = getAssociatedTokenAddressSync(
+      giftMintPda,
+      recipient.publicKey,
+      false,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    await program.methods
+      .processAffiliateFreshMint(null, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts({
+        campaign: freshCampaignPda,
+        affiliateLink: freshLinkPda,
+        sale: {
+          buyer: buyer.publicKey,
+          payer: buyer.publicKey,
+          owner: company.publicKey,
+          marketplaceAuthority: marketplaceAuthorityPda,
+          treasury: treasury.publicKey,
+          priceFeed,
+        },
+        recipient: recipient.publicKey,
+        influencer: influencer.publicKey,
+        affiliateVault: PublicKey.findProgramAddressSync(
+          [Buffer.from("affiliate_vault"), freshLinkPda.toBuffer()],
+          program.programId
+        )[0],
+        mintAuthority: PublicKey.findProgramAddressSync(
+          [Buffer.from("mint_authority"), freshCampaignPda.toBuffer()],
+          program.programId
+        )[0],
+        nftMint: giftMintPda,
+        groupMint: PublicKey.findProgramAddressSync(
+          [Buffer.from("campaign_group"), freshCampaignPda.toBuffer()],
+          program.programId
+        )[0],
+        recipientTokenAccount,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const recipientAccount = await getAccount(
+      provider.connection,
+      recipientTokenAccount,
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    assert.equal(recipientAccount.amount, BigInt(1));
+    assert.equal(recipientAccount.owner.toBase58(), recipient.publicKey.toBase58());
+    // The buyer paid for everything, the recipient never needed any SOL
+    assert.equal(await provider.connection.getBalance(recipient.publicKey), 0);
+  });
+
+  it("Lets a sponsor pay rent for a buyer with no spare SOL", async () => {
+    const priceFeed = new PublicKey("6Lxrw71CTYtWaXR4noDbPSj9wKhkuYC84UVcNtQN5q4p");
+    const price = 100_000_000; // $15.00 at $150.00 per SOL
+    const [freshCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from("fresh-campaign")],
+      program.programId
+    );
+    const [freshLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), freshCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [sponsoredMintPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fresh_mint"), freshCampaignPda.toBuffer(), new anchor.BN(6).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const buyer = Keypair.generate();
+    const sponsor = Keypair.generate();
+    // Exactly the price: nothing left over for the mint or ATA rent
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, price),
+      "confirmed"
+    );
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(sponsor.publicKey, 1e9),
+      "confirmed"
+    );
+    const sponsorBalanceBefore = await provider.connection.getBalance(sponsor.publicKey);
+    const buyerTokenAccount = getAssociatedTokenAddressSync(
+      sponsoredMintPda,
+      buyer.publicKey,
+      false,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    await program.methods
+      .processAffiliateFreshMint(null, new anchor.BN(price), new anchor.BN(1))
+      .accounts({
+        campaign: freshCampaignPda,
+        affiliateLink: freshLinkPda,
+        sale: {
+          buyer: buyer.publicKey,
+          payer: sponsor.publicKey,
+          owner: company.publicKey,
+          marketplaceAuthority: marketplaceAuthorityPda,
+          treasury: treasury.publicKey,
+          priceFeed,
+        },
+        recipient: buyer.publicKey,
+        influencer: influencer.publicKey,
+        affiliateVault: PublicKey.findProgramAddressSync(
+          [Buffer.from("affiliate_vault"), freshLinkPda.toBuffer()],
+          program.programId
+        )[0],
+        mintAuthority: PublicKey.findProgramAddressSync(
+          [Buffer.from("mint_authority"), freshCampaignPda.toBuffer()],
+          program.programId
+        )[0],
+        nftMint: sponsoredMintPda,
+        groupMint: PublicKey.findProgramAddressSync(
+          [Buffer.from("campaign_group"), freshCampaignPda.toBuffer()],
+          program.programId
+        )[0],
+        recipientTokenAccount: buyerTokenAccount,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer, sponsor])
+      .rpc();
+
+    const buyerAccount = await getAccount(
+      provider.connection,
+      buyerTokenAccount,
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    assert.equal(buyerAccount.amount, BigInt(1));
+    assert.equal(await provider.connection.getBalance(buyer.publicKey), 0);
+    assert.isBelow(await provider.connection.getBalance(sponsor.publicKey), sponsorBalanceBefore);
+  });
+
+  it("Mints a fresh NFT even if someone pre-funded its address", async () => {
+    const priceFeed = new PublicKey("6Lxrw71CTYtWaXR4noDbPSj9wKhkuYC84UVcNtQN5q4p");
+    const price = 100_000_000; // $15.00 at $150.00 per SOL
+    const [freshCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from("fresh-campaign")],
+      program.programId
+    );
+    const [freshLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), freshCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [nextMintPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fresh_mint"), freshCampaignPda.toBuffer(), new anchor.BN(7).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    // A plain transfer to the predictable next mint address used to brick the campaign
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        SystemProgram.transfer({
+          fromPubkey: provider.publicKey,
+          toPubkey: nextMintPda,
+          lamports: 1,
+        })
+      )
+    );
+
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 1e9),
+      "confirmed"
+    );
+    const buyerTokenAccount = getAssociatedTokenAddressSync(
+      nextMintPda,
+      buyer.publicKey,
+      false,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await program.methods
+      .processAffiliateFreshMint(null, new anchor.BN(price), new anchor.BN(1))
+      .accounts({
+        campaign: freshCampaignPda,
+        affiliateLink: freshLinkPda,
+        sale: {
+          buyer: buyer.publicKey,
+          payer: buyer.publicKey,
+          owner: company.publicKey,
+          marketplaceAuthority: marketplaceAuthorityPda,
+          treasury: treasury.publicKey,
+          priceFeed,
+        },
+        recipient: buyer.publicKey,
+        influencer: influencer.publicKey,
+        affiliateVault: PublicKey.findProgramAddressSync(
+          [Buffer.from("affiliate_vault"), freshLinkPda.toBuffer()],
+          program.programId
+        )[0],
+        mintAuthority: PublicKey.findProgramAddressSync(
+          [Buffer.from("mint_authority"), freshCampaignPda.toBuffer()],
+          program.programId
+        )[0],
+        nftMint: nextMintPda,
+        groupMint: PublicKey.findProgramAddressSync(
+          [Buffer.from("campaign_group"), freshCampaignPda.toBuffer()],
+          program.programId
+        )[0],
+        recipientTokenAccount: buyerTokenAccount,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const buyerAccount = await getAccount(
+      provider.connection,
+      buyerTokenAccount,
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    assert.equal(buyerAccount.amount, BigInt(1));
+    const mintInfo = await provider.connection.getAccountInfo(nextMintPda);
+    assert.equal(mintInfo.owner.toBase58(), TOKEN_2022_PROGRAM_ID.toBase58());
+    const campaignAccount = await program.account.nftCampaign.fetch(freshCampaignPda);
+    assert.equal(campaignAccount.freshMintCount.toNumber(), 8);
+  });
+
+  it("Sells directly without an affiliate link", async () => {
+    const price = 2_000_000; // Set by the campaign update above
+    const nft = await createMainCampaignNft();
+    await depositCompanyNft(mainCampaign, nft);
+
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 1e9),
+      "confirmed"
+    );
+    const buyerTokenAccount = getAssociatedTokenAddressSync(
+      nft.mint,
+      buyer.publicKey,
+      false,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const campaignBefore = await program.account.nftCampaign.fetch(campaignPda);
+    const companyBalanceBefore = await provider.connection.getBalance(company.publicKey);
+
+    await program.methods
+      .purchaseDirect(null, new anchor.BN(price))
+      .accounts({
+        campaign: campaignPda,
+        sale: {
+          buyer: buyer.publicKey,
+          payer: buyer.publicKey,
+          owner: company.publicKey,
+          marketplaceAuthority: marketplaceAuthorityPda,
+          treasury: treasury.publicKey,
+        },
+        recipient: buyer.publicKey,
+        nftMint: nft.mint,
+        nftMetadata: metadataPda(nft.mint),
+        nftEscrow: nftEscrowPda,
+        recipientTokenAccount: buyerTokenAccount,
+        escrowPdaNftTokenAccount: mainCampaign.escrowAccountFor(nft.mint),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    const buyerAccount = await getAccount(
+      provider.connection,
+      buyerTokenAccount,
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    assert.equal(buyerAccount.amount, BigInt(1));
+
+    // No commission: the company gets everything but the protocol fee
+    const fee = Math.floor(price * protocolFeeBps / 10_000);
+    const companyBalanceAfter = await provider.connection.getBalance(company.publicKey);
+    assert.equal(companyBalanceAfter - companyBalanceBefore, price - fee);
+
+    const campaignAfter = await program.account.nftCampaign.fetch(campaignPda);
+    assert.equal(campaignAfter.directSales.toNumber(), campaignBefore.directSales.toNumber() + 1);
+    assert.equal(campaignAfter.availableSupply.toNumber(), campaignBefore.availableSupply.toNumber() - 1);
+    assert.equal(campaignAfter.totalMints.toString(), campaignBefore.totalMints.toString());
+  });
+
+  it("Pays creator royalties from the NFT's Metaplex metadata", async () => {
+    // The fixture's metadata takes a 500 bps seller fee, split 70/30 between the two creators below
+    const royaltyNft = await createCompanyNft({ fixture: "royalty" });
+    const creators = [
+      new PublicKey("E3M3d7sy8ZKivUGxBexL9wxE7ebqzGWFqkdeFMedCJFS"),
+      new PublicKey("E7GLmRLyircx4ZXTcxHFSpizMop3fLC5QAJJJjhDwda9"),
+    ];
+    const {
+      campaign: royaltyCampaignPda,
+      escrow: royaltyEscrowPda,
+      escrowAccountFor,
+    } = await createEscrowCampaign("royalty-campaign", royaltyNft);
+    const royaltyEscrowTokenAccount = escrowAccountFor(royaltyNft.mint);
+
+    const [royaltyLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), royaltyCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [royaltyVaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_vault"), royaltyLinkPda.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createAffiliateLink()
+      .accounts({
+        affiliateLink: royaltyLinkPda,
+        campaign: royaltyCampaignPda,
+        affiliateVault: royaltyVaultPda,
+        marketplaceAuthority: marketplaceAuthorityPda,
+        influencer: influencer.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([influencer])
+      .rpc();
+
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+
+    const purchase = (creatorAccounts: PublicKey[]) =>
+      program.methods
+        .processAffiliateMint(null, mintPrice, new anchor.BN(1))
+        .accounts({
+          campaign: royaltyCampaignPda,
+          affiliateLink: royaltyLinkPda,
+          sale: {
+            buyer: buyer.publicKey,
+            payer: buyer.publicKey,
+            owner: company.publicKey,
+            marketplaceAuthority: marketplaceAuthorityPda,
+            treasury: treasury.publicKey,
+          },
+          recipient: buyer.publicKey,
+          influencer: influencer.publicKey,
+          affiliateVault: royaltyVaultPda,
+          nftMint: royaltyNft.mint,
+          nftMetadata: metadataPda(royaltyNft.mint),
+          nftEscrow: royaltyEscrowPda,
+          recipientTokenAccount: getAssociatedTokenAddressSync(
+            royaltyNft.mint,
+            buyer.publicKey,
+            false,
+            TOKEN_PROGRAM_ID
+          ),
+          escrowPdaNftTokenAccount: royaltyEscrowTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          creatorAccounts.map((pubkey) => ({ pubkey, isWritable: true, isSigner: false }))
+        )
+        .signers([buyer])
+        .rpc();
+
+    // Creators have to be passed in metadata order
+    try {
+      await purchase([...creators].reverse());
+      assert.fail("Creators out of order should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidCreatorAccount");
+    }
+
+    // The creators have never been funded, so the payer tops them up to rent exemption
+    const creatorBalancesBefore = await Promise.all(
+      creators.map((creator) => provider.connection.getBalance(creator))
+    );
+    creatorBalancesBefore.forEach((balance) => assert.equal(balance, 0));
+    const rentExempt = await provider.connection.getMinimumBalanceForRentExemption(0);
+    const companyBalanceBefore = await provider.connection.getBalance(company.publicKey);
+    await purchase(creators);
+
+    // Royalties come out of the company's share; fee and commission are unchanged
+    const price = mintPrice.toNumber();
+    const saleAmount = price - protocolFee;
+    const royalty = Math.floor(saleAmount * 500 / 10_000);
+    const creatorAmounts = [Math.floor(royalty * 70 / 100), Math.floor(royalty * 30 / 100)];
+    const creatorBalancesAfter = await Promise.all(
+      creators.map((creator) => provider.connection.getBalance(creator))
+    );
+    creators.forEach((_, i) =>
+      assert.equal(creatorBalancesAfter[i], Math.max(creatorAmounts[i], rentExempt))
+    );
+    const companyBalanceAfter = await provider.connection.getBalance(company.publicKey);
+    assert.equal(
+      companyBalanceAfter - companyBalanceBefore,
+      saleAmount - expectedCommission - creatorAmounts[0] - creatorAmounts[1]
+    );
+    const link = await program.account.affiliateLink.fetch(royaltyLinkPda);
+    assert.equal(link.unclaimedEarnings.toString(), expectedCommission.toString());
+  });
+
+  it("Pays each NFT's own royalties in a multi-NFT purchase", async () => {
+    // The fixture's metadata takes a 1000 bps seller fee, all to the creator below
+    const creator = new PublicKey("EBBduiozK9vBCemy4FcAjhVkby2FLPstxZxxN7jpgxtr");
+    // The first NFT has no metadata, the second carries royalties
+    const plain = await createCompanyNft();
+    const bundled = await createCompanyNft({ fixture: "bundle" });
+
+    const bundleCampaign = await createEscrowCampaign("bundle-campaign", plain);
+    const { campaign: bundleCampaignPda, escrow: bundleEscrowPda, escrowAccountFor } = bundleCampaign;
+    await depositCompanyNft(bundleCampaign, bundled);
+
+    const [bundleLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), bundleCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [bundleVaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_vault"), bundleLinkPda.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createAffiliateLink()
+      .accounts({
+        affiliateLink: bundleLinkPda,
+        campaign: bundleCampaignPda,
+        affiliateVault: bundleVaultPda,
+        marketplaceAuthority: marketplaceAuthorityPda,
+        influencer: influencer.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([influencer])
+      .rpc();
+
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+    const buyerAccountFor = (mint: PublicKey) =>
+      getAssociatedTokenAddressSync(mint, buyer.publicKey, false, TOKEN_PROGRAM_ID);
+    const purchase = (creatorAccounts: PublicKey[]) =>
+      program.methods
+        .processAffiliateMint(null, mintPrice.muln(2), new anchor.BN(2))
+        .accounts({
+          campaign: bundleCampaignPda,
+          affiliateLink: bundleLinkPda,
+          sale: {
+            buyer: buyer.publicKey,
+            payer: buyer.publicKey,
+            owner: company.publicKey,
+            marketplaceAuthority: marketplaceAuthorityPda,
+            treasury: treasury.publicKey,
+          },
+          recipient: buyer.publicKey,
+          influencer: influencer.publicKey,
+          affiliateVault: bundleVaultPda,
+          nftMint: plain.mint,
+          nftMetadata: metadataPda(plain.mint),
+          nftEscrow: bundleEscrowPda,
+          recipientTokenAccount: buyerAccountFor(plain.mint),
+          escrowPdaNftTokenAccount: escrowAccountFor(plain.mint),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts([
+          { pubkey: bundled.mint, isWritable: false, isSigner: false },
+          { pubkey: escrowAccountFor(bundled.mint), isWritable: true, isSigner: false },
+          { pubkey: buyerAccountFor(bundled.mint), isWritable: true, isSigner: false },
+          { pubkey: metadataPda(bundled.mint), isWritable: false, isSigner: false },
+          ...creatorAccounts.map((pubkey) => ({ pubkey, isWritable: true, isSigner: false })),
+        ])
+        .signers([buyer])
+        .rpc();
+
+    // Leading with an NFT without royalties doesn't skip the second NFT's creators
+    try {
+      await purchase([]);
+      assert.fail("A purchase missing the second NFT's creators should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidRemainingAccounts");
+    }
+
+    const rentExempt = await provider.connection.getMinimumBalanceForRentExemption(0);
+    const companyBalanceBefore = await provider.connection.getBalance(company.publicKey);
+    await purchase([creator]);
+
+    const saleAmount = mintPrice.toNumber() - protocolFee;
+    const royalty = Math.floor(saleAmount * 1000 / 10_000);
+    assert.equal(await provider.connection.getBalance(creator), Math.max(royalty, rentExempt));
+    const companyBalanceAfter = await provider.connection.getBalance(company.publicKey);
+    assert.equal(
+      companyBalanceAfter - companyBalanceBefore,
+      2 * (saleAmount - expectedCommission) - royalty
+    );
+    for (const mint of [plain.mint, bundled.mint]) {
+      const account = await getAccount(provider.connection, buyerAccountFor(mint), "confirmed", TOKEN_PROGRAM_ID);
+      assert.equal(account.amount, BigInt(1));
+    }
+  });
+
+  it("Buys several escrowed NFTs in one purchase", async () => {
+    const nfts = [await createCompanyNft(), await createCompanyNft(), await createCompanyNft()];
+    const multiCampaign = await createEscrowCampaign("multi-campaign", nfts[0]);
+    const { campaign: multiCampaignPda, escrow: multiEscrowPda, escrowAccountFor } = multiCampaign;
+    for (const nft of nfts.slice(1)) {
+      await depositCompanyNft(multiCampaign, nft);
+    }
+
+    const [multiLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), multiCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [multiVaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_vault"), multiLinkPda.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createAffiliateLink()
+      .accounts({
+        affiliateLink: multiLinkPda,
+        campaign: multiCampaignPda,
+        affiliateVault: multiVaultPda,
+        marketplaceAuthority: marketplaceAuthorityPda,
+        influencer: influencer.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([influencer])
+      .rpc();
+
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+    const buyerAccountFor = (mint: PublicKey) =>
+      getAssociatedTokenAddressSync(mint, buyer.publicKey, false, TOKEN_PROGRAM_ID);
+    // The first NFT goes in the named accounts, each extra one in remaining_accounts
+    const purchase = (extraNfts: { mint: PublicKey; escrowAccount: PublicKey }[]) =>
+      program.methods
+        .processAffiliateMint(null, mintPrice.muln(2), new anchor.BN(2))
+        .accounts({
+          campaign: multiCampaignPda,
+          affiliateLink: multiLinkPda,
+          sale: {
+            buyer: buyer.publicKey,
+            payer: buyer.publicKey,
+            owner: company.publicKey,
+            marketplaceAuthority: marketplaceAuthorityPda,
+            treasury: treasury.publicKey,
+          },
+          recipient: buyer.publicKey,
+          influencer: influencer.publicKey,
+          affiliateVault: multiVaultPda,
+          nftMint: nfts[0].mint,
+          nftMetadata: metadataPda(nfts[0].mint),
+          nftEscrow: multiEscrowPda,
+          recipientTokenAccount: buyerAccountFor(nfts[0].mint),
+          escrowPdaNftTokenAccount: escrowAccountFor(nfts[0].mint),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          extraNfts.flatMap(({ mint, escrowAccount }) => [
+            { pubkey: mint, isWritable: false, isSigner: false },
+            { pubkey: escrowAccount, isWritable: true, isSigner: false },
+            { pubkey: buyerAccountFor(mint), isWritable: true, isSigner: false },
+            { pubkey: metadataPda(mint), isWritable: false, isSigner: false },
+          ])
+        )
+        .signers([buyer])
+        .rpc();
+
+    // Every NFT past the first needs its accounts
+    try {
+      await purchase([]);
+      assert.fail("A purchase missing the second NFT's accounts should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidRemainingAccounts");
+    }
+    // ... and has to come out of the campaign escrow's own token account for it
+    try {
+      await purchase([{ mint: nfts[1].mint, escrowAccount: escrowAccountFor(nfts[2].mint) }]);
+      assert.fail("An escrow account of another mint should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidRemainingAccounts");
+    }
+
+    const vaultBalanceBefore = await provider.connection.getBalance(multiVaultPda);
+    await purchase([{ mint: nfts[1].mint, escrowAccount: escrowAccountFor(nfts[1].mint) }]);
+
+    for (const nft of nfts.slice(0, 2)) {
+      const account = await getAccount(provider.connection, buyerAccountFor(nft.mint), "confirmed", TOKEN_PROGRAM_ID);
+      assert.equal(account.amount, BigInt(1));
+    }
+    const vaultBalanceAfter = await provider.connection.getBalance(multiVaultPda);
+    assert.equal(vaultBalanceAfter - vaultBalanceBefore, 2 * expectedCommission);
+    const campaignAccount = await program.account.nftCampaign.fetch(multiCampaignPda);
+    assert.equal(campaignAccount.totalMints.toNumber(), 2);
+    assert.equal(campaignAccount.availableSupply.toNumber(), 1);
+    const link = await program.account.affiliateLink.fetch(multiLinkPda);
+    assert.equal(link.mintsCount.toNumber(), 2);
+  });
+
+  it("Only escrows verified items of the campaign collection", async () => {
+    // The item's metadata fixture marks it a verified member of the collection fixture
+    const collectionMint = loadKeypair("tests/fixtures/collection_mint.json");
+    await createMint(provider.connection, company, company.publicKey, null, 0, collectionMint, undefined, TOKEN_PROGRAM_ID);
+    const item = await createCompanyNft({ fixture: "collection" });
+
+    const collectionCampaign = await createEscrowCampaign("collection-campaign", item, {
+      collectionMint: collectionMint.publicKey,
+    });
+    const campaignAccount = await program.account.nftCampaign.fetch(collectionCampaign.campaign);
+    assert.equal(campaignAccount.collectionMint.toBase58(), collectionMint.publicKey.toBase58());
+
+    // A mint the company controls but that isn't in the collection is refused
+    const outsider = await createCompanyNft();
+    try {
+      await depositCompanyNft(collectionCampaign, outsider);
+      assert.fail("NFTs outside the collection should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "NotCollectionMember");
+    }
+  });
+
+  it("Checks the NFT is a one-of-one and can lock its mint authority", async () => {
+    // Two tokens of the same mint can't be sold as a one-of-one
+    const duplicated = await createCompanyNft({ supply: 2 });
+    try {
+      await createEscrowCampaign("duplicated-campaign", duplicated);
+      assert.fail("A mint with more than the escrowed supply should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "NftSupplyMismatch");
+    }
+
+    const locked = await createCompanyNft();
+    const lockedCampaign = await createEscrowCampaign("locked-campaign", locked, { lock: { campaign: {} } });
+    let mintAccount = await getMint(provider.connection, locked.mint, "confirmed", TOKEN_PROGRAM_ID);
+    assert.equal(mintAccount.mintAuthority.toBase58(), lockedCampaign.mintAuthority.toBase58());
+
+    // Deposits into an existing campaign get the same checks
+    try {
+      await depositCompanyNft(lockedCampaign, duplicated);
+      assert.fail("Depositing a mint with more than one token should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "NftSupplyMismatch");
+    }
+    const fungible = await createCompanyNft({ decimals: 6 });
+    try {
+      await depositCompanyNft(lockedCampaign, fungible);
+      assert.fail("Depositing a mint with decimals should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidNftDecimals");
+    }
+
+    const revoked = await createCompanyNft();
+    await createEscrowCampaign("revoked-campaign", revoked, { lock: { revoke: {} } });
+    mintAccount = await getMint(provider.connection, revoked.mint, "confirmed", TOKEN_PROGRAM_ID);
+    assert.isNull(mintAccount.mintAuthority);
+  });
+
+  it("Adds an NFT locked to the campaign to its group", async () => {
+    // Token-2022 one-of-one that can point at its own group member entry
+    const createMemberNft = async () => {
+      const mint = Keypair.generate();
+      const mintLen = getMintLen([ExtensionType.GroupMemberPointer]);
+      const account = getAssociatedTokenAddressSync(mint.publicKey, company.publicKey, false, TOKEN_2022_PROGRAM_ID);
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          SystemProgram.createAccount({
+            fromPubkey: provider.publicKey,
+            newAccountPubkey: mint.publicKey,
+            space: mintLen,
+            lamports: await provider.connection.getMinimumBalanceForRentExemption(mintLen),
+            programId: TOKEN_2022_PROGRAM_ID,
+          }),
+          createInitializeGroupMemberPointerInstruction(
+            mint.publicKey,
+            company.publicKey,
+            mint.publicKey,
+            TOKEN_2022_PROGRAM_ID
+          ),
+          createInitializeMintInstruction(mint.publicKey, 0, company.publicKey, null, TOKEN_2022_PROGRAM_ID),
+          createAssociatedTokenAccountInstruction(
+            provider.publicKey,
+            account,
+            company.publicKey,
+            mint.publicKey,
+            TOKEN_2022_PROGRAM_ID
+          ),
+          createMintToInstruction(mint.publicKey, account, company.publicKey, 1, [], TOKEN_2022_PROGRAM_ID)
+        ),
+        [mint, company]
+      );
+      return { mint: mint.publicKey, account };
+    };
+    const setUpCampaign = async (name: string, lock: object) => {
+      const nft = await createMemberNft();
+      const { campaign: campaignPda, mintAuthority: mintAuthorityPda } = await createEscrowCampaign(name, nft, {
+        lock,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      });
+      const [groupMintPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("campaign_group"), campaignPda.toBuffer()],
+        program.programId
+      );
+      const createGroup = () =>
+        program.methods
+          .createCampaignGroup(10)
+          .accounts({
+            company: company.publicKey,
+            campaign: campaignPda,
+            mintAuthority: mintAuthorityPda,
+            groupMint: groupMintPda,
+            nftMint: nft.mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([company])
+          .rpc();
+      return { nft, campaignPda, mintAuthorityPda, groupMintPda, createGroup };
+    };
+
+    // The campaign's mint_authority PDA now holds the mint and signs the membership
+    const locked = await setUpCampaign("group-locked-campaign", { campaign: {} });
+    await locked.createGroup();
+    await program.methods
+      .addGroupMember()
+      .accounts({
+        company: company.publicKey,
+        campaign: locked.campaignPda,
+        mintAuthority: locked.mintAuthorityPda,
+        groupMint: locked.groupMintPda,
+        nftMint: locked.nft.mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([company])
+      .rpc();
+    const mint = await getMint(provider.connection, locked.nft.mint, "confirmed", TOKEN_2022_PROGRAM_ID);
+    assert.equal(getTokenGroupMemberState(mint).group.toBase58(), locked.groupMintPda.toBase58());
+
+    // Nobody can ever add a revoked NFT, so its campaign can't get a group it couldn't sell in
+    const revoked = await setUpCampaign("group-revoked-campaign", { revoke: {} });
+    try {
+      await revoked.createGroup();
+      assert.fail("A group for a campaign whose NFT can't join should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "CampaignNftCannotJoinGroup");
+    }
+  });
+
+  it("Draws a random escrowed NFT and delivers it to the buyer", async () => {
+    const nfts = [await createCompanyNft(), await createCompanyNft(), await createCompanyNft()];
+    const randomCampaign = await createEscrowCampaign("random-campaign", nfts[0]);
+    const { campaign: randomCampaignPda, escrow: randomEscrowPda, escrowAccountFor } = randomCampaign;
+    const [inventoryPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("inventory"), randomCampaignPda.toBuffer()],
+      program.programId
+    );
+    for (const nft of nfts.slice(1)) {
+      await depositCompanyNft(randomCampaign, nft);
+    }
+
+    // Listing only part of the escrow would leave NFTs nobody can draw
+    const createInventory = (listed: typeof nfts) =>
+      program.methods
+        .createCampaignInventory()
+        .accounts({
+          company: company.publicKey,
+          campaign: randomCampaignPda,
+          nftEscrow: randomEscrowPda,
+          inventory: inventoryPda,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          listed.flatMap((nft) =>
+            [nft.mint, escrowAccountFor(nft.mint), metadataPda(nft.mint)].map((pubkey) => ({
+              pubkey,
+              isWritable: false,
+              isSigner: false,
+            }))
+          )
+        )
+        .signers([company])
+        .rpc();
+    try {
+      await createInventory(nfts.slice(1));
+      assert.fail("An inventory missing escrowed NFTs should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InventoryOutOfSync");
+    }
+    await createInventory(nfts);
+    const campaignAccount = await program.account.nftCampaign.fetch(randomCampaignPda);
+    assert.deepEqual(campaignAccount.selectionMode, { random: {} });
+
+    const [randomLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), influencer.publicKey.toBuffer(), randomCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [randomVaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_vault"), randomLinkPda.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createAffiliateLink()
+      .accounts({
+        affiliateLink: randomLinkPda,
+        campaign: randomCampaignPda,
+        affiliateVault: randomVaultPda,
+        marketplaceAuthority: marketplaceAuthorityPda,
+        influencer: influencer.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([influencer])
+      .rpc();
+
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2e9),
+      "confirmed"
+    );
+    const randomDrawFor = (sequence: anchor.BN) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("random_draw"), randomCampaignPda.toBuffer(), sequence.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      )[0];
+    // No mint is named: deliver_random_draw picks it from a slot hash after the purchase
+    const purchase = (randomDraw: PublicKey) =>
+      program.methods
+        .processAffiliateMint(null, mintPrice, new anchor.BN(1))
+        .accounts({
+          campaign: randomCampaignPda,
+          affiliateLink: randomLinkPda,
+          sale: {
+            buyer: buyer.publicKey,
+            payer: buyer.publicKey,
+            owner: company.publicKey,
+            marketplaceAuthority: marketplaceAuthorityPda,
+            treasury: treasury.publicKey,
+          },
+          recipient: buyer.publicKey,
+          influencer: influencer.publicKey,
+          affiliateVault: randomVaultPda,
+          nftEscrow: randomEscrowPda,
+          randomDraw,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc({ commitment: "confirmed" });
+    const sequence = new anchor.BN(0);
+    const randomDrawPda = randomDrawFor(sequence);
+    const companyBalanceBefore = await provider.connection.getBalance(company.publicKey);
+    const signature = await purchase(randomDrawPda);
+
+    // The company share waits in the draw until the drawn NFT's royalties are known
+    const draw = await program.account.randomDraw.fetch(randomDrawPda);
+    assert.equal(draw.buyer.toBase58(), buyer.publicKey.toBase58());
+    assert.equal(draw.recipient.toBase58(), buyer.publicKey.toBase58());
+    const companyShare = mintPrice.toNumber() - protocolFee - expectedCommission;
+    assert.equal(draw.heldAmount.toNumber(), companyShare);
+    assert.equal(await provider.connection.getBalance(company.publicKey), companyBalanceBefore);
+    const purchaseTx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    assert.equal(draw.drawSlot.toNumber(), purchaseTx.slot);
+    // Nothing is drawn yet, so the inventory still lists every NFT
+    assert.equal((await program.account.campaignInventory.fetch(inventoryPda)).mints.length, 3);
+
+    // A draw whose slot hash is still in the window can't be renewed
+    const renew = (randomDraw: PublicKey, renewer: Keypair) =>
+      program.methods
+        .renewRandomDraw()
+        .accounts({
+          company: renewer.publicKey,
+          campaign: randomCampaignPda,
+          randomDraw,
+          slotHashes: anchor.web3.SYSVAR_SLOT_HASHES_PUBKEY,
+        })
+        .signers([renewer])
+        .rpc({ commitment: "confirmed" });
+    try {
+      await renew(randomDrawPda, company);
+      assert.fail("A draw that can still be delivered should not be renewed");
+    } catch (err) {
+      assert.include(err.toString(), "DrawNotExpired");
+    }
+
+    // The draw uses the hash of the first slot after the purchase, once it is in SlotHashes
+    const firstSlotHashAfter = async (drawSlot: number) => {
+      for (;;) {
+        const data = (await provider.connection.getAccountInfo(anchor.web3.SYSVAR_SLOT_HASHES_PUBKEY, "confirmed")).data;
+        const entries = Number(data.readBigUInt64LE(0));
+        let found: { slot: number; hash: Buffer } | undefined;
+        for (let i = 0; i < entries; i++) {
+          const offset = 8 + i * 40;
+          const slot = Number(data.readBigUInt64LE(offset));
+          if (slot <= drawSlot) break;
+          found = { slot, hash: data.subarray(offset + 8, offset + 40) };
+        }
+        if (found) return found;
+        await new Promise((resolve) => setTimeout(resolve, 400));
+      }
+    };
+    const slotHash = await firstSlotHashAfter(draw.drawSlot.toNumber());
+    const seed = createHash("sha256")
+      .update(slotHash.hash)
+      .update(buyer.publicKey.toBuffer())
+      .update(sequence.toArrayLike(Buffer, "le", 8))
+      .digest();
+    const listedMints = (await program.account.campaignInventory.fetch(inventoryPda)).mints;
+    const index = Number(seed.readBigUInt64LE(0) % BigInt(listedMints.length));
+    const drawnMint = listedMints[index];
+    const otherMint = listedMints.find((mint) => !mint.equals(drawnMint));
+
+    // Anyone can deliver the draw, but only with the NFT it picks; its rent goes back to the payer
+    const deliver = (nftMint: PublicKey) =>
+      program.methods
+        .deliverRandomDraw()
+        .accounts({
+          payer: provider.publicKey,
+          campaign: randomCampaignPda,
+          randomDraw: randomDrawPda,
+          recipient: buyer.publicKey,
+          rentPayer: buyer.publicKey,
+          inventory: inventoryPda,
+          slotHashes: anchor.web3.SYSVAR_SLOT_HASHES_PUBKEY,
+          nftMint,
+          nftMetadata: metadataPda(nftMint),
+          company: company.publicKey,
+          nftEscrow: randomEscrowPda,
+          escrowPdaNftTokenAccount: escrowAccountFor(nftMint),
+          recipientTokenAccount: getAssociatedTokenAddressSync(nftMint, buyer.publicKey, false, TOKEN_PROGRAM_ID),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ commitment: "confirmed" });
+    try {
+      await deliver(otherMint);
+      assert.fail("Delivering an NFT the draw didn't pick should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidRandomDraw");
+    }
+    const deliverSignature = await deliver(drawnMint);
+
+    // The emitted seed inputs reproduce the drawn index
+    const deliverTx = await provider.connection.getTransaction(deliverSignature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const events = [...new anchor.EventParser(program.programId, program.coder).parseLogs(deliverTx.meta.logMessages)];
+    const drawEvent = events.find((event) => event.name === "randomDrawEvent").data;
+    assert.equal(drawEvent.slot.toNumber(), slotHash.slot);
+    assert.deepEqual(drawEvent.slotHash, [...slotHash.hash]);
+    assert.deepEqual(drawEvent.seed, [...seed]);
+    assert.equal(drawEvent.inventoryLen.toNumber(), 3);
+    assert.equal(drawEvent.index.toNumber(), index);
+    assert.equal(drawEvent.nftMint.toBase58(), drawnMint.toBase58());
+    const inventory = await program.account.campaignInventory.fetch(inventoryPda);
+    assert.equal(inventory.mints.length, 2);
+    assert.notInclude(inventory.mints.map((mint) => mint.toBase58()), drawnMint.toBase58());
+
+    const buyerTokenAccount = getAssociatedTokenAddressSync(drawnMint, buyer.publicKey, false, TOKEN_PROGRAM_ID);
+    const buyerAccount = await getAccount(provider.connection, buyerTokenAccount, "confirmed", TOKEN_PROGRAM_ID);
+    assert.equal(buyerAccount.amount, BigInt(1));
+    assert.isNull(await provider.connection.getAccountInfo(randomDrawPda));
+    // The drawn NFT has no metadata, so the whole held share goes to the company
+    assert.equal(
+      await provider.connection.getBalance(company.publicKey),
+      companyBalanceBefore + companyShare
+    );
+    const campaignAfter = await program.account.nftCampaign.fetch(randomCampaignPda);
+    assert.equal(campaignAfter.pendingDraws.toNumber(), 0);
+    assert.equal(campaignAfter.availableSupply.toNumber(), 2);
+
+    // Once a draw expires only the company can renew it, so the buyer can't reroll an outcome
+    // they saw and left undelivered. Expiry takes the ~512 slots SlotHashes keeps.
+    const expiringDrawPda = randomDrawFor(new anchor.BN(1));
+    await purchase(expiringDrawPda);
+    const expiringSlot = (await program.account.randomDraw.fetch(expiringDrawPda)).drawSlot.toNumber();
+    for (;;) {
+      const data = (await provider.connection.getAccountInfo(anchor.web3.SYSVAR_SLOT_HASHES_PUBKEY, "confirmed")).data;
+      const entries = Number(data.readBigUInt64LE(0));
+      if (Number(data.readBigUInt64LE(8 + (entries - 1) * 40)) > expiringSlot) break;
+      await new Promise((resolve) => setTimeout(resolve, 2_000));
+    }
+    try {
+      await renew(expiringDrawPda, buyer);
+      assert.fail("Only the company should renew an expired draw");
+    } catch (err) {
+      assert.include(err.toString(), "Unauthorized");
+    }
+    const renewSignature = await renew(expiringDrawPda, company);
+    const renewTx = await provider.connection.getTransaction(renewSignature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const renewedDraw = await program.account.randomDraw.fetch(expiringDrawPda);
+    assert.equal(renewedDraw.drawSlot.toNumber(), renewTx.slot);
+  });
+
+  it("Prices sales with a Dutch auction and quotes the live price", async () => {
+    const startPrice = 3_000_000;
+    const endPrice = 1_000_000;
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    const setAuction = (startTime: number, decayInterval: number) =>
+      program.methods
+        .setDutchAuction({
+          startPrice: new anchor.BN(startPrice),
+          endPrice: new anchor.BN(endPrice),
+          startTime: new anchor.BN(startTime),
+          decayInterval: new anchor.BN(decayInterval),
+        })
+        .accounts({ company: company.publicKey, campaign: campaignPda })
+        .signers([company])
+        .rpc();
+    const quote = () =>
+      program.methods.quotePrice(null).accounts({ campaign: campaignPda }).view();
+
+    // Nothing sells before the auction opens
+    await setAuction(now + 1_000, 100);
+    try {
+      await quote();
+      assert.fail("Quotes before the start time should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "AuctionNotStarted");
+    }
+
+    // Halfway through the decay the price sits between the two ends
+    await setAuction(now - 500, 1_000);
+    const liveQuote = (await quote()).toNumber();
+    assert.isBelow(liveQuote, startPrice);
+    assert.isAbove(liveQuote, endPrice);
+
+    // Past the decay interval the price stays at the end price, and the commission
+    // is taken from what the buyer actually pays
+    await setAuction(now - 1_000, 100);
+    assert.equal((await quote()).toNumber(), endPrice);
+
+    const nft = await createMainCampaignNft();
+    await depositCompanyNft(mainCampaign, nft);
+
+    const buyer = Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 1e9),
+      "confirmed"
+    );
+    const vaultBalanceBefore = await provider.connection.getBalance(affiliateVaultPda);
+    const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+    await program.methods
+      .processAffiliateMint(null, new anchor.BN(endPrice), new anchor.BN(1))
+      .accounts({
+        campaign: campaignPda,
+        affiliateLink: affiliateLinkPda,
+        sale: {
+          buyer: buyer.publicKey,
+          payer: provider.publicKey,
+          owner: company.publicKey,
+          marketplaceAuthority: marketplaceAuthorityPda,
+          treasury: treasury.publicKey,
+        },
+        recipient: buyer.publicKey,
+        influencer: influencer.publicKey,
+        affiliateVault: affiliateVaultPda,
+        nftMint: nft.mint,
+        nftMetadata: metadataPda(nft.mint),
+        nftEscrow: nftEscrowPda,
+        recipientTokenAccount: getAssociatedTokenAddressSync(
+          nft.mint,
+          buyer.publicKey,
+          false,
+          TOKEN_2022_PROGRAM_ID
+        ),
+        escrowPdaNftTokenAccount: mainCampaign.escrowAccountFor(nft.mint),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([buyer])
+      .rpc();
+
+    // The provider pays rent and fees, so the buyer's balance only drops by the price
+    const buyerBalanceAfter = await provider.connection.getBalance(buyer.publicKey);
+    assert.equal(buyerBalanceBefore - buyerBalanceAfter, endPrice);
+    const fee = Math.floor(endPrice * protocolFeeBps / 10_000);
+    const vaultBalanceAfter = await provider.connection.getBalance(affiliateVaultPda);
+    assert.equal(
+      vaultBalanceAfter - vaultBalanceBefore,
+      Math.floor((endPrice - fee) * commissionBps / 10_000)
+    );
+
+    // Clearing the auction goes back to the fixed mint price
+    await program.methods
+      .setDutchAuction(null)
+      .accounts({ company: company.publicKey, campaign: campaignPda })
+      .signers([company])
+      .rpc();
+    const campaignAccount = await program.account.nftCampaign.fetch(campaignPda);
+    assert.isNull(campaignAccount.auction);
+  });
+
+  it("Closes a campaign's price table along with it", async () => {
+    const [usdcCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), company.publicKey.toBuffer(), Buffer.from("usdc-campaign")],
+      program.programId
+    );
+    const [priceTablePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("price_table"), usdcCampaignPda.toBuffer()],
+      program.programId
+    );
+    const companyBalanceBefore = await provider.connection.getBalance(company.publicKey);
+    const priceTableRent = await provider.connection.getBalance(priceTablePda);
+    assert.isAbove(priceTableRent, 0);
+
+    await program.methods
+      .closeCampaign()
+      .accounts({
+        company: company.publicKey,
+        campaign: usdcCampaignPda,
+        nftEscrow: PublicKey.findProgramAddressSync(
+          [Buffer.from("nft_escrow"), usdcCampaignPda.toBuffer()],
+          program.programId
+        )[0],
+        priceTable: priceTablePda,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([company])
+      .rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(priceTablePda));
+    assert.isAbove(
+      (await provider.connection.getBalance(company.publicKey)) - companyBalanceBefore,
+      priceTableRent
+    );
+  });
+
+  it("Migrates a name-keyed campaign and affiliate link", async () => {
+    // Legacy accounts are loaded from fixtures; the keypairs match the keys recorded in them
+    const legacyCompany = loadKeypair("tests/fixtures/legacy_company.json");
+    const legacyInfluencer = loadKeypair("tests/fixtures/legacy_influencer.json");
+    const legacyMint = loadKeypair("tests/fixtures/legacy_nft_mint.json");
+    const legacyName = "legacy-campaign";
+    for (const wallet of [legacyCompany, legacyInfluencer]) {
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(wallet.publicKey, 1e9),
+        "confirmed"
+      );
+    }
+
+    const [legacyCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), Buffer.from(legacyName)],
+      program.programId
+    );
+    const [legacyEscrowPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_escrow"), legacyCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [migratedCampaignPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_campaign"), legacyCompany.publicKey.toBuffer(), Buffer.from(legacyName)],
+      program.programId
+    );
+    const [migratedEscrowPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_escrow"), migratedCampaignPda.toBuffer()],
+      program.programId
+    );
+
+    // The legacy escrow holds the campaign NFT, as the old create instruction left it
+    await createMint(
+      provider.connection,
+      legacyCompany,
+      legacyCompany.publicKey,
+      null,
+      0,
+      legacyMint,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const legacyEscrowTokenAccount = getAssociatedTokenAddressSync(
+      legacyMint.publicKey,
+      legacyEscrowPda,
+      true,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        createAssociatedTokenAccountInstruction(
+          legacyCompany.publicKey,
+          legacyEscrowTokenAccount,
+          legacyEscrowPda,
+          legacyMint.publicKey,
+          TOKEN_2022_PROGRAM_ID
+        ),
+        createMintToInstruction(
+          legacyMint.publicKey,
+          legacyEscrowTokenAccount,
+          legacyCompany.publicKey,
+          1,
+          [],
+          TOKEN_2022_PROGRAM_ID
+        )
+      ),
+      [legacyCompany]
+    );
+    const migratedEscrowTokenAccount = getAssociatedTokenAddressSync(
+      legacyMint.publicKey,
+      migratedEscrowPda,
+      true,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    await program.methods
+      .migrateCampaign(legacyName)
+      .accounts({
+        company: legacyCompany.publicKey,
+        legacyCampaign: legacyCampaignPda,
+        campaign: migratedCampaignPda,
+        nftMint: legacyMint.publicKey,
+        legacyNftEscrow: legacyEscrowPda,
+        legacyEscrowTokenAccount,
+        nftEscrow: migratedEscrowPda,
+        escrowPdaNftTokenAccount: migratedEscrowTokenAccount,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([legacyCompany])
+      .rpc();
+
+    const migratedCampaign = await program.account.nftCampaign.fetch(migratedCampaignPda);
+    assert.equal(migratedCampaign.company.toBase58(), legacyCompany.publicKey.toBase58());
+    assert.equal(migratedCampaign.mintPrice.toNumber(), 1_000_000);
+    // Whole percents become basis points
+    assert.equal(migratedCampaign.commissionBps, 500);
+    assert.equal(migratedCampaign.totalMints.toNumber(), 3);
+    assert.equal(migratedCampaign.availableSupply.toNumber(), 1);
+    const migratedEscrow = await getAccount(
+      provider.connection,
+      migratedEscrowTokenAccount,
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    assert.equal(migratedEscrow.amount, BigInt(1));
+    assert.isNull(await provider.connection.getAccountInfo(legacyEscrowTokenAccount));
+    assert.isNull(await provider.connection.getAccountInfo(legacyCampaignPda));
+
+    const [legacyLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), legacyInfluencer.publicKey.toBuffer(), Buffer.from(legacyName)],
+      program.programId
+    );
+    const [migratedLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), legacyInfluencer.publicKey.toBuffer(), migratedCampaignPda.toBuffer()],
+      program.programId
+    );
+    const [migratedVaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_vault"), migratedLinkPda.toBuffer()],
+      program.programId
+    );
+    assert.equal(migratedCampaign.legacyCampaign.toBase58(), legacyCampaignPda.toBase58());
+
+    // Another company's campaign with the same name can't take over the legacy stats
+    const impostor = await createEscrowCampaign(legacyName, await createCompanyNft());
+    const [impostorLinkPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("affiliate_link"), legacyInfluencer.publicKey.toBuffer(), impostor.campaign.toBuffer()],
+      program.programId
+    );
+    try {
+      await program.methods
+        .migrateAffiliateLink()
+        .accounts({
+          influencer: legacyInfluencer.publicKey,
+          campaign: impostor.campaign,
+          legacyAffiliateLink: legacyLinkPda,
+          affiliateLink: impostorLinkPda,
+          affiliateVault: PublicKey.findProgramAddressSync(
+            [Buffer.from("affiliate_vault"), impostorLinkPda.toBuffer()],
+            program.programId
+          )[0],
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([legacyInfluencer])
+        .rpc();
+      assert.fail("A link should only migrate onto the campaign its legacy campaign became");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidLegacyAccount");
+    }
+
+    await program.methods
+      .migrateAffiliateLink()
+      .accounts({
+        influencer: legacyInfluencer.publicKey,
+        campaign: migratedCampaignPda,
+        legacyAffiliateLink: legacyLinkPda,
+        affiliateLink: migratedLinkPda,
+        affiliateVault: migratedVaultPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([legacyInfluencer])
+      .rpc();
+
+    const migratedLink = await program.account.affiliateLink.fetch(migratedLinkPda);
+    assert.equal(migratedLink.campaign.toBase58(), migratedCampaignPda.toBase58());
+    assert.equal(migratedLink.mintsCount.toNumber(), 3);
+    // Legacy commissions were already paid out, so nothing is left to claim
+    assert.equal(migratedLink.earnings.toNumber(), 150_000);
+    assert.equal(migratedLink.claimedEarnings.toNumber(), 150_000);
+    assert.equal(migratedLink.unclaimedEarnings.toNumber(), 0);
+    assert.equal(
+      await provider.connection.getBalance(migratedVaultPda),
+      await provider.connection.getMinimumBalanceForRentExemption(0)
+    );
+    assert.isNull(await provider.connection.getAccountInfo(legacyLinkPda));
+  });
+
+  it("Closes the campaign and refunds the company", async () => {
+    await program.methods
+      .closeCampaign()
+      .accounts({
+        company: company.publicKey,
+        campaign: campaignPda,
+        nftMint: nftMint.publicKey,
+        nftEscrow: nftEscrowPda,
+        escrowPdaNftTokenAccount: escrowTokenAccount,
+        projectTokenAccount: companyTokenAccount,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([company])
+      .rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(campaignPda));
+    assert.isNull(await provider.connection.getAccountInfo(escrowTokenAccount));
+  });
+
   
 });
   